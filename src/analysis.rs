@@ -0,0 +1,397 @@
+// Static analysis of ElfCode programs by abstract interpretation over value intervals.
+//
+// Every register holds an interval before each instruction, a constant when both ends
+// are equal. Jumps go to every target the written ip can take. Bounds still growing
+// after a few times around a loop are widened to infinity, so the analysis ends.
+// Arithmetic is assumed not to overflow, as with checked arithmetic.
+//
+// Registers a loop never writes keep their value from the loop entry. Constant ones
+// are the numbers a program was generated with, e.g. the number day 19 sums divisors of.
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Write};
+
+use crate::elfcode::OpCode::*;
+use crate::elfcode::{Instruction, Operand, VM};
+
+// Visits of a loop start before its bounds are widened.
+const WIDEN_AFTER: u32 = 3;
+
+// Values from lo to hi, with i64::MIN and i64::MAX standing for unbounded.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Interval {
+    pub lo: i64,
+    pub hi: i64,
+}
+
+impl Interval {
+    pub const ANY: Interval = Interval {
+        lo: i64::MIN,
+        hi: i64::MAX,
+    };
+
+    pub fn constant(v: i64) -> Interval {
+        Interval { lo: v, hi: v }
+    }
+
+    fn new(lo: i128, hi: i128) -> Interval {
+        let clamp = |v: i128| v.clamp(i64::MIN as i128, i64::MAX as i128) as i64;
+        Interval {
+            lo: clamp(lo),
+            hi: clamp(hi),
+        }
+    }
+
+    pub fn value(&self) -> Option<i64> {
+        if self.lo == self.hi {
+            Some(self.lo)
+        } else {
+            None
+        }
+    }
+
+    fn join(self, other: Interval) -> Interval {
+        Interval {
+            lo: self.lo.min(other.lo),
+            hi: self.hi.max(other.hi),
+        }
+    }
+
+    // Bounds of next that grew compared to self become unbounded.
+    fn widen(self, next: Interval) -> Interval {
+        Interval {
+            lo: if next.lo < self.lo { i64::MIN } else { next.lo },
+            hi: if next.hi > self.hi { i64::MAX } else { next.hi },
+        }
+    }
+
+    fn bounds(self) -> (i128, i128) {
+        let bound = |v: i64| match v {
+            i64::MIN => i128::MIN / 4,
+            i64::MAX => i128::MAX / 4,
+            v => v as i128,
+        };
+        (bound(self.lo), bound(self.hi))
+    }
+
+    fn add(self, other: Interval) -> Interval {
+        let ((a, b), (c, d)) = (self.bounds(), other.bounds());
+        Interval::new(a + c, b + d)
+    }
+
+    fn mul(self, other: Interval) -> Interval {
+        let ((a, b), (c, d)) = (self.bounds(), other.bounds());
+        // Products of unbounded ends saturate, keeping signs.
+        let products = [
+            a.saturating_mul(c),
+            a.saturating_mul(d),
+            b.saturating_mul(c),
+            b.saturating_mul(d),
+        ];
+        Interval::new(
+            *products.iter().min().unwrap(),
+            *products.iter().max().unwrap(),
+        )
+    }
+
+    fn bitwise(self, other: Interval, and: bool) -> Interval {
+        if let (Some(a), Some(b)) = (self.value(), other.value()) {
+            return Interval::constant(if and { a & b } else { a | b });
+        }
+        if self.lo < 0 || other.lo < 0 {
+            return Interval::ANY;
+        }
+        if and {
+            Interval {
+                lo: 0,
+                hi: self.hi.min(other.hi),
+            }
+        } else {
+            // All bits up to the highest one of either.
+            let hi = self.hi.max(other.hi);
+            let bits = 64 - hi.leading_zeros();
+            Interval {
+                lo: self.lo.max(other.lo),
+                hi: if bits >= 63 {
+                    i64::MAX
+                } else {
+                    (1 << bits) - 1
+                },
+            }
+        }
+    }
+
+    fn compare(self, other: Interval, greater: bool) -> Interval {
+        let known = if greater {
+            if self.lo > other.hi {
+                Some(1)
+            } else if self.hi <= other.lo {
+                Some(0)
+            } else {
+                None
+            }
+        } else if self.value().is_some() && self.value() == other.value() {
+            Some(1)
+        } else if self.lo > other.hi || self.hi < other.lo {
+            Some(0)
+        } else {
+            None
+        };
+        known.map_or(Interval { lo: 0, hi: 1 }, Interval::constant)
+    }
+}
+
+impl fmt::Display for Interval {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.lo, self.hi) {
+            (lo, hi) if lo == hi => write!(f, "{}", lo),
+            (i64::MIN, i64::MAX) => write!(f, "?"),
+            (i64::MIN, hi) => write!(f, "..={}", hi),
+            (lo, i64::MAX) => write!(f, "{}..", lo),
+            (lo, hi) => write!(f, "{}..={}", lo, hi),
+        }
+    }
+}
+
+// Result of an instruction for register intervals r.
+fn transfer(ins: &Instruction, r: &[Interval]) -> Interval {
+    let value = |op| match op {
+        Operand::Register(i) => r.get(i).copied().unwrap_or(Interval::ANY),
+        Operand::Immediate(v) => Interval::constant(v),
+        Operand::Unused => Interval::constant(0),
+    };
+    let [a, b] = ins.inputs();
+    let (a, b) = (value(a), value(b));
+    match ins.opcode {
+        addr | addi => a.add(b),
+        mulr | muli => a.mul(b),
+        banr | bani => a.bitwise(b, true),
+        borr | bori => a.bitwise(b, false),
+        setr | seti => a,
+        gtir | gtri | gtrr => a.compare(b, true),
+        eqir | eqri | eqrr => a.compare(b, false),
+        Extension(op) => match (a.value(), b.value()) {
+            (Some(a), Some(b)) => op.apply(a, b).map_or(Interval::ANY, Interval::constant),
+            _ => Interval::ANY,
+        },
+    }
+}
+
+// Instructions start..=end, repeated through a jump from end back to start.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Loop {
+    pub start: usize,
+    pub end: usize,
+    // Registers not written in the loop, other than ip.
+    pub invariant: Vec<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Analysis {
+    // Register intervals before each instruction, None if it can't be reached.
+    pub states: Vec<Option<Vec<Interval>>>,
+    pub loops: Vec<Loop>,
+}
+
+// Analyze vm starting with registers in the given intervals.
+pub fn analyze(vm: &VM, initial: &[Interval]) -> Analysis {
+    let n = vm.program.len();
+    let mut states: Vec<Option<Vec<Interval>>> = vec![None; n];
+    let mut visits = vec![0; n];
+    let mut back_edges: BTreeMap<usize, usize> = BTreeMap::new();
+    let mut work = BTreeSet::new();
+    if n > 0 {
+        states[0] = Some(initial.to_vec());
+        work.insert(0);
+    }
+
+    while let Some(i) = work.pop_first() {
+        let ins = &vm.program[i];
+        let mut r = states[i].clone().unwrap();
+        let result = transfer(ins, &r);
+        if let Some(c) = r.get_mut(ins.c as usize) {
+            *c = result;
+        }
+
+        // The VM continues after the instruction ip is set to.
+        let targets = match vm.ip_register {
+            Some(ip) if ip == ins.c as usize => {
+                let (lo, hi) = (result.lo.saturating_add(1), result.hi.saturating_add(1));
+                if hi < 0 || lo >= n as i64 {
+                    0..0
+                } else {
+                    lo.max(0) as usize..(hi.min(n as i64 - 1) + 1) as usize
+                }
+            }
+            _ => i + 1..(i + 2).min(n),
+        };
+
+        for t in targets {
+            if t <= i {
+                let end = back_edges.entry(t).or_insert(i);
+                *end = (*end).max(i);
+            }
+            if let Some(ip) = vm.ip_register {
+                r[ip] = Interval::constant(t as i64);
+            }
+            let next = match &states[t] {
+                None => r.clone(),
+                Some(old) => old
+                    .iter()
+                    .zip(r.iter())
+                    .map(|(&o, &v)| {
+                        let joined = o.join(v);
+                        if t <= i && visits[t] >= WIDEN_AFTER {
+                            o.widen(joined)
+                        } else {
+                            joined
+                        }
+                    })
+                    .collect(),
+            };
+            if states[t].as_ref() != Some(&next) {
+                states[t] = Some(next);
+                visits[t] += 1;
+                work.insert(t);
+            }
+        }
+    }
+
+    let loops = back_edges
+        .into_iter()
+        .map(|(start, end)| {
+            let written: BTreeSet<usize> = vm.program[start..=end]
+                .iter()
+                .map(|ins| ins.c as usize)
+                .collect();
+            let invariant = (0..initial.len())
+                .filter(|r| !written.contains(r) && Some(*r) != vm.ip_register)
+                .collect();
+            Loop {
+                start,
+                end,
+                invariant,
+            }
+        })
+        .collect();
+
+    Analysis { states, loops }
+}
+
+impl Analysis {
+    // Registers the program computes before a loop that keeps them constant, with
+    // their values. These are the program's inputs.
+    pub fn inputs(&self, vm: &VM) -> Vec<(usize, i64)> {
+        let mut inputs = BTreeSet::new();
+        for l in &self.loops {
+            if let Some(r) = &self.states[l.start] {
+                for &i in &l.invariant {
+                    let written = vm.program.iter().any(|ins| ins.c as usize == i);
+                    if let Some(v) = r[i].value().filter(|_| written) {
+                        inputs.insert((i, v));
+                    }
+                }
+            }
+        }
+        inputs.into_iter().collect()
+    }
+
+    // Program listing with the registers known before each instruction, then loops
+    // with their invariant registers and the inputs.
+    pub fn report(&self, vm: &VM) -> String {
+        let mut out = String::new();
+        let names = vm.register_names();
+        let known = |r: &[Interval]| {
+            (0..r.len())
+                .filter(|&i| r[i] != Interval::ANY && Some(i) != vm.ip_register)
+                .map(|i| format!("{}={}", names[i], r[i]))
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+
+        for (ip, (ins, state)) in vm.program.iter().zip(self.states.iter()).enumerate() {
+            let state = match state {
+                Some(r) => known(r),
+                None => "unreachable".to_string(),
+            };
+            writeln!(
+                out,
+                "{:>4}: {:<20}{:<40}{}",
+                ip,
+                ins.to_string(),
+                vm.decompile(ins),
+                state
+            )
+            .unwrap();
+        }
+
+        writeln!(out, "loops:").unwrap();
+        for l in &self.loops {
+            let mut line = format!("{:>10}  invariant", format!("{}..={}", l.start, l.end));
+            if let Some(r) = &self.states[l.start] {
+                for &i in &l.invariant {
+                    write!(line, " {}={}", names[i], r[i]).unwrap();
+                }
+            }
+            writeln!(out, "{}", line).unwrap();
+        }
+
+        writeln!(out, "inputs:").unwrap();
+        for (r, v) in self.inputs(vm) {
+            writeln!(out, "{:>4} = {}", names[r], v).unwrap();
+        }
+
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a = 0; for b in 0..10 { a += 7 }, with 7 computed first.
+    const PROGRAM: &str = "#ip 5
+seti 3 0 2
+addi 2 4 2
+seti 0 0 0
+seti 0 0 1
+addr 0 2 0
+addi 1 1 1
+gtri 1 9 3
+addr 3 5 5
+seti 3 0 5
+mulr 0 0 0";
+
+    #[test]
+    fn test_intervals() {
+        let i = |lo, hi| Interval { lo, hi };
+        assert_eq!(i(1, 3).mul(i(-2, 5)), i(-6, 15));
+        assert_eq!(i(0, i64::MAX).add(i(-5, -1)), i(-5, i64::MAX));
+        assert_eq!(i(0, 5).bitwise(i(8, 8), false), i(8, 15));
+        assert_eq!(i(0, 5).compare(i(6, 9), true), Interval::constant(0));
+        assert_eq!(i(0, 5).compare(i(5, 9), false), i(0, 1));
+        assert_eq!(i(0, 5).widen(i(0, 7)), i(0, i64::MAX));
+        assert_eq!(i(i64::MIN, 3).to_string(), "..=3");
+    }
+
+    #[test]
+    fn test_analyze() {
+        let vm: VM = PROGRAM.parse().unwrap();
+        let analysis = analyze(&vm, &[Interval::constant(0); 6]);
+        let before = |ip: usize, r: usize| analysis.states[ip].as_ref().unwrap()[r];
+
+        assert_eq!(before(4, 2), Interval::constant(7));
+        assert_eq!(before(4, 1).lo, 0);
+        assert_eq!(before(7, 3), Interval { lo: 0, hi: 1 });
+        assert!(analysis.states.iter().all(|s| s.is_some()));
+        assert_eq!(
+            analysis.loops,
+            vec![Loop {
+                start: 4,
+                end: 8,
+                invariant: vec![2, 4],
+            }]
+        );
+        assert_eq!(analysis.inputs(&vm), vec![(2, 7)]);
+        assert!(analysis.report(&vm).ends_with("\ninputs:\n   c = 7\n"));
+    }
+}
@@ -0,0 +1,98 @@
+use std::convert::TryFrom;
+use std::fmt;
+
+// Answer to one part of a day. Display gives the answer in the format it is submitted in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Answer {
+    Integer(i64),
+    Text(String),
+    // Submitted as comma separated values, e.g. x,y or x,y,size.
+    Coordinates(Vec<i64>),
+    // Answer is drawn in an image file at path and has to be read by eye.
+    Image(String),
+}
+
+impl fmt::Display for Answer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Answer::Integer(n) => write!(f, "{}", n),
+            Answer::Text(s) => write!(f, "{}", s),
+            Answer::Coordinates(c) => {
+                let c: Vec<String> = c.iter().map(|v| v.to_string()).collect();
+                write!(f, "{}", c.join(","))
+            }
+            Answer::Image(path) => write!(f, "{}", path),
+        }
+    }
+}
+
+impl From<i64> for Answer {
+    fn from(n: i64) -> Answer {
+        Answer::Integer(n)
+    }
+}
+
+impl From<u64> for Answer {
+    fn from(n: u64) -> Answer {
+        Answer::Integer(i64::try_from(n).expect("answer too large"))
+    }
+}
+
+impl From<usize> for Answer {
+    fn from(n: usize) -> Answer {
+        Answer::Integer(i64::try_from(n).expect("answer too large"))
+    }
+}
+
+impl From<u32> for Answer {
+    fn from(n: u32) -> Answer {
+        Answer::Integer(n.into())
+    }
+}
+
+impl From<String> for Answer {
+    fn from(s: String) -> Answer {
+        Answer::Text(s)
+    }
+}
+
+impl From<(i64, i64)> for Answer {
+    fn from((x, y): (i64, i64)) -> Answer {
+        Answer::Coordinates(vec![x, y])
+    }
+}
+
+impl From<(usize, usize)> for Answer {
+    fn from((x, y): (usize, usize)) -> Answer {
+        Answer::Coordinates(vec![x as i64, y as i64])
+    }
+}
+
+impl From<(i64, i64, i64)> for Answer {
+    fn from((x, y, z): (i64, i64, i64)) -> Answer {
+        Answer::Coordinates(vec![x, y, z])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        assert_eq!(Answer::from(3020072891u64).to_string(), "3020072891");
+        assert_eq!(
+            Answer::from("0124515891".to_string()).to_string(),
+            "0124515891"
+        );
+        assert_eq!(Answer::from((245i64, 14i64)).to_string(), "245,14");
+        assert_eq!(
+            Answer::from((235i64, 206i64, 13i64)).to_string(),
+            "235,206,13"
+        );
+        assert_eq!(
+            Answer::Image("outputs/day10.png".to_string()).to_string(),
+            "outputs/day10.png"
+        );
+    }
+}
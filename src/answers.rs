@@ -0,0 +1,136 @@
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use crate::error::ParseError;
+
+// Known correct answers, e.g. per day in inputs/answers.toml or
+// per example in inputs/examples/dayN/answers.toml.
+// Only the subset of TOML needed is supported:
+// [name] tables with part1 and part2 keys holding strings or integers, and # comments.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Answers {
+    tables: BTreeMap<String, [Option<String>; 2]>,
+}
+
+impl Answers {
+    // Recorded answer for part 1 or 2 in table, e.g. "day4".
+    pub fn get(&self, table: &str, part: usize) -> Option<&str> {
+        self.tables
+            .get(table)
+            .and_then(|parts| parts.get(part.checked_sub(1)?))
+            .and_then(|a| a.as_deref())
+    }
+}
+
+// Value after "=", either a quoted string or an integer, optionally followed by a comment.
+fn parse_value(s: &str) -> Option<String> {
+    let (value, rest) = if let Some(quoted) = s.strip_prefix('"') {
+        let mut value = String::new();
+        let mut chars = quoted.char_indices();
+        let end = loop {
+            match chars.next()? {
+                (i, '"') => break i + 1,
+                (_, '\\') => match chars.next()?.1 {
+                    c @ '"' | c @ '\\' => value.push(c),
+                    _ => return None,
+                },
+                (_, c) => value.push(c),
+            }
+        };
+        (value, &quoted[end..])
+    } else {
+        let end = s
+            .find(|c: char| c.is_whitespace() || c == '#')
+            .unwrap_or(s.len());
+        let value = s[..end].trim_start_matches('+');
+        value.parse::<i64>().ok()?;
+        (value.to_string(), &s[end..])
+    };
+    let rest = rest.trim();
+    if rest.is_empty() || rest.starts_with('#') {
+        Some(value)
+    } else {
+        None
+    }
+}
+
+impl FromStr for Answers {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut answers = Answers::default();
+        let mut table = None;
+
+        for (i, l) in s.lines().enumerate() {
+            let line = l.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if line.starts_with('[') {
+                let name = line
+                    .strip_prefix('[')
+                    .and_then(|t| t.strip_suffix(']'))
+                    .filter(|t| {
+                        !t.is_empty()
+                            && t.chars()
+                                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+                    })
+                    .ok_or_else(|| ParseError::new(l, "table like [day4]").offset(i))?;
+                answers.tables.entry(name.to_string()).or_default();
+                table = Some(name);
+                continue;
+            }
+
+            const EXPECTED: &str = "answer like part1 = 42 or part2 = \"abc\"";
+            let err = || ParseError::new(l, EXPECTED).offset(i);
+            let eq = line.find('=').ok_or_else(err)?;
+            let part = match line[..eq].trim() {
+                "part1" => 0,
+                "part2" => 1,
+                _ => return Err(err()),
+            };
+            let value = parse_value(line[eq + 1..].trim()).ok_or_else(err)?;
+            let t = table.ok_or_else(|| ParseError::new(l, "table before answers").offset(i))?;
+            answers.tables.get_mut(t).unwrap()[part] = Some(value);
+        }
+
+        Ok(answers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let answers: Answers = r#"
+# comment
+[day1]
+part1 = 493
+part2 = -413 # comment
+
+[day2]
+part1 = "a \"b\" c"
+"#
+        .parse()
+        .unwrap();
+
+        assert_eq!(answers.get("day1", 1), Some("493"));
+        assert_eq!(answers.get("day1", 2), Some("-413"));
+        assert_eq!(answers.get("day2", 1), Some("a \"b\" c"));
+        assert_eq!(answers.get("day2", 2), None);
+        assert_eq!(answers.get("day3", 1), None);
+        assert_eq!(answers.get("day1", 0), None);
+    }
+
+    #[test]
+    fn test_parse_error() {
+        let err = "[day1]\npart1 = abc".parse::<Answers>().unwrap_err();
+        assert_eq!(err.line, Some(2));
+        assert!("part1 = 1".parse::<Answers>().is_err());
+        assert!("[day1]\npart3 = 1".parse::<Answers>().is_err());
+        assert!("[day1]\npart1 = \"abc".parse::<Answers>().is_err());
+        assert!("[day 1]\npart1 = 1".parse::<Answers>().is_err());
+    }
+}
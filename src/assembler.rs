@@ -0,0 +1,296 @@
+// Assembler for ElfCode programs with labels, register names, constants and comments,
+// producing the plain "#ip N" and "opcode a b c" format `VM` parses.
+//
+//     ; sum of 1..=10 into a
+//     #ip r5
+//     .reg a 0
+//     .reg i 1
+//     .const LIMIT 10
+//             seti 0 _ a
+//     next:   addi i 1 i
+//             addr a i a
+//             eqri i LIMIT r2
+//             addr r2 ip ip
+//             jmp next
+//
+// Comments start with ";". Registers are r0 to r5, numbers, names given with
+// ".reg name index" or ip for the bound register. Immediates are numbers, names given
+// with ".const name value", or labels. A label is a jump target in an instruction
+// writing ip: "seti label _ ip" and "jmp label" jump to it and "addi ip label ip"
+// jumps relative to the instruction, elsewhere it is the index of the instruction.
+// Operands an instruction doesn't use can be written as "_".
+use std::collections::HashMap;
+
+use crate::elfcode::{Instruction, InstructionSet, OpCode, Operand, REGISTERS};
+use crate::error::{parse_as, ParseError};
+
+struct Symbols {
+    ip: Option<usize>,
+    registers: HashMap<String, usize>,
+    constants: HashMap<String, i64>,
+    labels: HashMap<String, usize>,
+}
+
+fn is_name(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+impl Symbols {
+    fn define(&mut self, name: &str, line: &str) -> Result<(), ParseError> {
+        let defined = self.registers.contains_key(name)
+            || self.constants.contains_key(name)
+            || self.labels.contains_key(name)
+            || name == "ip"
+            || name == "_";
+        if !is_name(name) || defined {
+            return Err(ParseError::new(line, "new name"));
+        }
+        Ok(())
+    }
+
+    fn register(&self, s: &str) -> Result<usize, ParseError> {
+        let err = || ParseError::new(s, "register like r2, 2 or a name given with .reg");
+        let r = match (s, self.registers.get(s)) {
+            (_, Some(&r)) => r,
+            ("ip", None) => self.ip.ok_or_else(err)?,
+            _ => s
+                .strip_prefix('r')
+                .unwrap_or(s)
+                .parse()
+                .map_err(|_| err())?,
+        };
+        if r >= REGISTERS {
+            return Err(err());
+        }
+        Ok(r)
+    }
+
+    // Immediate operand, jump holds the index of the instruction if it writes ip
+    // and whether the jump is relative to it.
+    fn immediate(&self, s: &str, jump: Option<(usize, bool)>) -> Result<i64, ParseError> {
+        if let Some(&v) = self.constants.get(s) {
+            return Ok(v);
+        }
+        if let Some(&label) = self.labels.get(s) {
+            return Ok(match jump {
+                Some((_, false)) => label as i64 - 1,
+                Some((i, true)) => label as i64 - i as i64 - 1,
+                None => label as i64,
+            });
+        }
+        parse_as(s, "value like 42, a constant or a label")
+    }
+}
+
+// Split off a leading "label:".
+fn take_label(line: &str) -> Option<(&str, &str)> {
+    let colon = line.find(':')?;
+    let label = line[..colon].trim();
+    if is_name(label) {
+        Some((label, line[colon + 1..].trim()))
+    } else {
+        None
+    }
+}
+
+pub fn assemble(source: &str) -> Result<String, ParseError> {
+    assemble_with(source, &InstructionSet::default())
+}
+
+// Assemble a program using opcodes of the given set.
+pub fn assemble_with(source: &str, set: &InstructionSet) -> Result<String, ParseError> {
+    let mut symbols = Symbols {
+        ip: None,
+        registers: HashMap::new(),
+        constants: HashMap::new(),
+        labels: HashMap::new(),
+    };
+    let mut instructions: Vec<(usize, &str)> = vec![];
+
+    // First pass: directives and labels.
+    for (i, l) in source.lines().enumerate() {
+        let mut line = l.split(';').next().unwrap().trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words[0] {
+            "#ip" => {
+                if words.len() != 2 || symbols.ip.is_some() {
+                    return Err(ParseError::new(l, "one ip binding like #ip r3").offset(i));
+                }
+                symbols.ip = Some(symbols.register(words[1]).map_err(|e| e.offset(i))?);
+                continue;
+            }
+            ".reg" | ".const" => {
+                if words.len() != 3 {
+                    return Err(ParseError::new(l, "definition like .reg a 0").offset(i));
+                }
+                symbols.define(words[1], l).map_err(|e| e.offset(i))?;
+                if words[0] == ".reg" {
+                    let r = symbols.register(words[2]).map_err(|e| e.offset(i))?;
+                    symbols.registers.insert(words[1].to_string(), r);
+                } else {
+                    let v = parse_as(words[2], "value like 42").map_err(|e| e.offset(i))?;
+                    symbols.constants.insert(words[1].to_string(), v);
+                }
+                continue;
+            }
+            w if w.starts_with('.') || w.starts_with('#') => {
+                return Err(ParseError::new(l, "directive #ip, .reg or .const").offset(i))
+            }
+            _ => {}
+        }
+
+        while let Some((label, rest)) = take_label(line) {
+            symbols.define(label, l).map_err(|e| e.offset(i))?;
+            symbols.labels.insert(label.to_string(), instructions.len());
+            line = rest;
+        }
+        if !line.is_empty() {
+            instructions.push((i, line));
+        }
+    }
+
+    // Second pass: instructions.
+    let mut out = String::new();
+    if let Some(ip) = symbols.ip {
+        out += &format!("#ip {}\n", ip);
+    }
+    for (index, &(i, line)) in instructions.iter().enumerate() {
+        let ins = assemble_instruction(&symbols, set, index, line).map_err(|e| e.offset(i))?;
+        out += &format!("{}\n", ins);
+    }
+
+    Ok(out)
+}
+
+fn assemble_instruction(
+    symbols: &Symbols,
+    set: &InstructionSet,
+    index: usize,
+    line: &str,
+) -> Result<Instruction, ParseError> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    if words[0] == "jmp" {
+        let ip = symbols
+            .ip
+            .ok_or_else(|| ParseError::new(line, "#ip before using jmp"))?;
+        if words.len() != 2 {
+            return Err(ParseError::new(line, "jump like jmp label"));
+        }
+        return Ok(Instruction {
+            opcode: OpCode::seti,
+            a: symbols.immediate(words[1], Some((index, false)))?,
+            b: 0,
+            c: ip as i64,
+        });
+    }
+
+    let opcode = set.opcode(words[0])?;
+    if words.len() != 4 {
+        return Err(ParseError::new(line, "instruction like seti 5 _ a"));
+    }
+    let c = symbols.register(words[3])?;
+    let writes_ip = symbols.ip == Some(c);
+    let kinds = Instruction {
+        opcode,
+        a: 0,
+        b: 0,
+        c: 0,
+    }
+    .inputs();
+
+    let mut values = [0; 2];
+    let mut reads_ip = false;
+    for (k, (value, kind)) in values.iter_mut().zip(kinds.iter()).enumerate() {
+        let s = words[k + 1];
+        *value = match kind {
+            Operand::Register(_) => {
+                let r = symbols.register(s)?;
+                reads_ip |= symbols.ip == Some(r);
+                r as i64
+            }
+            Operand::Immediate(_) => {
+                let jump = if writes_ip {
+                    Some((index, reads_ip))
+                } else {
+                    None
+                };
+                symbols.immediate(s, jump)?
+            }
+            Operand::Unused if s == "_" => 0,
+            Operand::Unused => parse_as(s, "unused operand like _ or 0")?,
+        };
+    }
+
+    Ok(Instruction {
+        opcode,
+        a: values[0],
+        b: values[1],
+        c: c as i64,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elfcode::VM;
+
+    #[test]
+    fn test_assemble() {
+        let source = "
+; sum of 1..=10 into a
+#ip r5
+.reg a 0
+.reg i 1
+.const LIMIT 10
+        seti 0 _ a
+next:   addi i 1 i      ; i += 1
+        addr a i a
+        eqri i LIMIT r2
+        addr r2 ip ip
+        jmp next
+end:
+";
+        let plain = assemble(source).unwrap();
+        assert_eq!(
+            plain,
+            "#ip 5
+seti 0 0 0
+addi 1 1 1
+addr 0 1 0
+eqri 1 10 2
+addr 2 5 5
+seti 0 0 5
+"
+        );
+
+        let mut vm: VM = plain.parse().unwrap();
+        vm.run().unwrap();
+        assert_eq!(vm.registers[0], 55);
+    }
+
+    #[test]
+    fn test_labels() {
+        let plain =
+            assemble("#ip 3\nstart: addi ip end ip\nseti start _ ip\nend: seti end _ 0").unwrap();
+        assert_eq!(plain, "#ip 3\naddi 3 1 3\nseti -1 0 3\nseti 2 0 0\n");
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(assemble("#ip 1\n\nseti x _ 0").unwrap_err().line, Some(3));
+        assert!(assemble("seti 1 _ r6").is_err());
+        assert!(assemble("jmp x\nx:").is_err());
+        assert!(assemble("x: seti 1 _ 0\nx: seti 2 _ 0").is_err());
+        assert!(assemble(".reg ip 2").is_err());
+        assert!(assemble("addr 1 _ 0").is_err());
+        assert!(assemble("nop").is_err());
+    }
+}
@@ -0,0 +1,137 @@
+use std::fmt;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use crate::error::Error;
+use crate::solution::Runner;
+
+// What is measured: parsing the input or solving one part from parsed input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    Parse,
+    Part1,
+    Part2,
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            Stage::Parse => "parse",
+            Stage::Part1 => "part1",
+            Stage::Part2 => "part2",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Stats {
+    pub iterations: usize,
+    pub min: Duration,
+    pub median: Duration,
+    pub mean: Duration,
+    pub stddev: Duration,
+}
+
+impl Stats {
+    pub fn new(samples: &[Duration]) -> Stats {
+        assert!(!samples.is_empty());
+        let mut sorted = samples.to_vec();
+        sorted.sort();
+        let n = sorted.len();
+        let median = (sorted[(n - 1) / 2] + sorted[n / 2]) / 2;
+        let secs: Vec<f64> = sorted.iter().map(|d| d.as_secs_f64()).collect();
+        let mean = secs.iter().sum::<f64>() / n as f64;
+        let variance = secs.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / n as f64;
+
+        Stats {
+            iterations: n,
+            min: sorted[0],
+            median,
+            mean: Duration::from_secs_f64(mean),
+            stddev: Duration::from_secs_f64(variance.sqrt()),
+        }
+    }
+}
+
+// Run f warmup times without measuring and then iterations times measuring each run.
+fn measure<F: FnMut()>(warmup: usize, iterations: usize, mut f: F) -> Stats {
+    for _ in 0..warmup {
+        f();
+    }
+    let samples: Vec<Duration> = (0..iterations)
+        .map(|_| {
+            let start = Instant::now();
+            f();
+            start.elapsed()
+        })
+        .collect();
+
+    Stats::new(&samples)
+}
+
+// Measure parsing and the given parts separately.
+// Each solve gets a fresh copy of the parsed input, the copying is not measured.
+pub fn bench(
+    runner: &dyn Runner,
+    input: &str,
+    stages: &[Stage],
+    warmup: usize,
+    iterations: usize,
+) -> Result<Vec<(Stage, Stats)>, Error> {
+    let parsed = runner.parse(input)?;
+    let mut results = vec![];
+
+    for &stage in stages {
+        let stats = match stage {
+            Stage::Parse => measure(warmup, iterations, || {
+                black_box(runner.parse(black_box(input)).unwrap());
+            }),
+            Stage::Part1 | Stage::Part2 => {
+                // A part failing to solve is reported instead of measured, days without
+                // a second part have nothing to measure. The check counts as the first
+                // warmup run.
+                let input = runner.clone_input(parsed.as_ref());
+                if stage == Stage::Part1 {
+                    runner.part1(input)?;
+                } else if runner.part2(input)?.is_none() {
+                    continue;
+                }
+                let warmup = warmup.saturating_sub(1);
+                let mut inputs: Vec<_> = (0..warmup + iterations)
+                    .map(|_| runner.clone_input(parsed.as_ref()))
+                    .collect();
+                measure(warmup, iterations, || {
+                    let input = inputs.pop().unwrap();
+                    // The results are kept from the optimizer so the work is not skipped.
+                    if stage == Stage::Part1 {
+                        black_box(runner.part1(input)).ok();
+                    } else {
+                        black_box(runner.part2(input)).ok();
+                    }
+                })
+            }
+        };
+        results.push((stage, stats));
+    }
+
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stats() {
+        let ms = Duration::from_millis;
+        let stats = Stats::new(&[ms(4), ms(2), ms(8), ms(6)]);
+        assert_eq!(stats.iterations, 4);
+        assert_eq!(stats.min, ms(2));
+        assert_eq!(stats.median, ms(5));
+        assert_eq!(stats.mean, ms(5));
+        assert!((stats.stddev.as_secs_f64() - 5e-6f64.sqrt()).abs() < 1e-9);
+
+        assert_eq!(Stats::new(&[ms(3), ms(1), ms(2)]).median, ms(2));
+    }
+}
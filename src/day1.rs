@@ -1,5 +1,9 @@
 use std::collections::HashSet;
 
+use crate::answer::Answer;
+use crate::error::{parse_as, Error, ParseError};
+use crate::solution::Solution;
+
 pub fn solve1(frequencies: Vec<i64>) -> i64 {
     frequencies.iter().sum()
 }
@@ -20,22 +24,24 @@ pub fn solve2(frequencies: Vec<i64>) -> i64 {
     panic!("no solution found!");
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+pub struct Day1;
+
+impl Solution for Day1 {
+    type Input = Vec<i64>;
+
+    fn parse(&self, input: &str) -> Result<Self::Input, ParseError> {
+        input
+            .lines()
+            .enumerate()
+            .map(|(i, l)| parse_as(l, "frequency change like +7 or -3").map_err(|e| e.offset(i)))
+            .collect()
+    }
 
-    #[test]
-    fn test1() {
-        assert_eq!(solve1(vec![1, 1, 1]), 3);
-        assert_eq!(solve1(vec![1, 1, -2]), 0);
-        assert_eq!(solve1(vec![-1, -2, -3]), -6);
+    fn part1(&self, input: Self::Input) -> Result<Answer, Error> {
+        Ok(solve1(input).into())
     }
 
-    #[test]
-    fn test2() {
-        assert_eq!(solve2(vec![1, -1]), 0);
-        assert_eq!(solve2(vec![3, 3, 4, -2, -4]), 10);
-        assert_eq!(solve2(vec![-6, 3, 8, 5, -6]), 5);
-        assert_eq!(solve2(vec![7, 7, -2, -7, -4]), 14);
+    fn part2(&self, input: Self::Input) -> Result<Option<Answer>, Error> {
+        Ok(Some(solve2(input).into()))
     }
 }
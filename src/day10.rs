@@ -1,7 +1,14 @@
+use std::path::Path;
 use std::str::FromStr;
 
 use regex::Regex;
 
+use crate::answer::Answer;
+use crate::error::{captures, field, Error, ParseError};
+use crate::parse_lines;
+use crate::solution::Solution;
+
+#[derive(Debug, Clone)]
 pub struct Point {
     x: i64,
     y: i64,
@@ -10,44 +17,41 @@ pub struct Point {
 }
 
 impl FromStr for Point {
-    type Err = Box<::std::error::Error>;
+    type Err = ParseError;
 
     // input: position=< 9,  1> velocity=< 0,  2>
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        const EXPECTED: &str = "point like position=< 9,  1> velocity=< 0,  2>";
         lazy_static! {
             static ref RE: Regex = Regex::new(
                 r"^position=<\s?(-?\d+), \s?(-?\d+)> velocity=<\s?(-?\d+), \s?(-?\d+)>$"
             )
             .unwrap();
         }
-        let cap = RE.captures(s).unwrap();
+        let cap = captures(&RE, s, EXPECTED)?;
 
         Ok(Point {
-            x: cap[1].parse()?,
-            y: cap[2].parse()?,
-            v_x: cap[3].parse()?,
-            v_y: cap[4].parse()?,
+            x: field(s, &cap, 1, EXPECTED)?,
+            y: field(s, &cap, 2, EXPECTED)?,
+            v_x: field(s, &cap, 3, EXPECTED)?,
+            v_y: field(s, &cap, 4, EXPECTED)?,
         })
     }
 }
 
 pub struct Sky {
     points: Vec<Point>,
-    size: usize,
     seconds: u64,
 }
 
 impl Sky {
-    fn variance(&self) -> u64 {
-        let mut x_count = vec![0; self.size];
-        let mut y_count = vec![0; self.size];
-        let offset: i64 = (self.size / 2) as i64;
-        for p in &self.points {
-            x_count[(p.x + offset) as usize] = 1;
-            y_count[(p.y + offset) as usize] = 1;
-        }
-
-        x_count.iter().sum::<u64>() + y_count.iter().sum::<u64>()
+    // Width plus height of the area covered by the points.
+    fn spread(&self) -> i64 {
+        let max_x = self.points.iter().map(|p| p.x).max().unwrap_or(0);
+        let min_x = self.points.iter().map(|p| p.x).min().unwrap_or(0);
+        let max_y = self.points.iter().map(|p| p.y).max().unwrap_or(0);
+        let min_y = self.points.iter().map(|p| p.y).min().unwrap_or(0);
+        (max_x - min_x) + (max_y - min_y)
     }
 
     fn forward(&mut self) {
@@ -66,7 +70,8 @@ impl Sky {
         self.seconds -= 1;
     }
 
-    fn print(&self, name: &str) {
+    // Draw the points white on black into a PNG at path, creating its directory.
+    fn render(&self, path: &Path) -> Result<(), Error> {
         let max_x = self.points.iter().max_by_key(|p| p.x).unwrap().x;
         let min_x = self.points.iter().min_by_key(|p| p.x).unwrap().x;
         let max_y = self.points.iter().max_by_key(|p| p.y).unwrap().y;
@@ -82,47 +87,74 @@ impl Sky {
                 }
             }
         }
-        let b = sky.iter().flatten().map(|s| *s).collect::<Vec<u8>>();
-
-        image::save_buffer(
-            &std::path::Path::new(&format!("outputs/{}.png", name)),
-            &b,
-            size as u32,
-            size as u32,
-            image::Gray(8),
-        )
-        .expect("writing image file failed");
+        let b = sky.iter().flatten().copied().collect::<Vec<u8>>();
+
+        let io_error = |err| Error::Io {
+            path: path.display().to_string(),
+            err,
+        };
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(io_error)?;
+        }
+        image::save_buffer(path, &b, size as u32, size as u32, image::Gray(8)).map_err(io_error)
     }
 }
 
-pub fn solve1(points: Vec<Point>, size: usize, variance: u64) {
-    let mut sky = Sky {
-        points,
-        size,
-        seconds: 0,
-    };
-    let mut last = sky.variance();
-    let mut s_last = last;
-    let mut cur = last;
-
-    while cur != variance {
-        // Use this to obtain variance value:
-        // if cur != last {
-        //     println!("variance: {}", cur);
-        //     sky.print(&cur.to_string());
-        // }
+// Image file with the message.
+const IMAGE: &str = "outputs/day10.png";
+
+// Points converge to the message and then drift apart again,
+// so the message is visible when they are closest together.
+fn message(points: Vec<Point>) -> Sky {
+    let mut sky = Sky { points, seconds: 0 };
+    let mut last = sky.spread();
+    // Spread can't shrink forever, also stops if the points don't move.
+    loop {
         sky.forward();
-        s_last = last;
+        let cur = sky.spread();
+        if cur >= last {
+            break;
+        }
         last = cur;
-        cur = sky.variance();
     }
-
-    println!("variance: {} {} {}", cur, last, s_last);
-    sky.print("day10_1");
-    println!("seconds_1: {}", sky.seconds);
     sky.backward();
-    sky.print("day10_2");
-    println!("seconds_1: {}", sky.seconds);
+    sky
+}
+
+// Draws the message into an image at path.
+pub fn solve1(points: Vec<Point>, path: &Path) -> Result<(), Error> {
+    message(points).render(path)
+}
+
+// Seconds until the message appears.
+pub fn solve2(points: Vec<Point>) -> u64 {
+    message(points).seconds
+}
+
+pub struct Day10;
+
+impl Solution for Day10 {
+    type Input = Vec<Point>;
+
+    fn parse(&self, input: &str) -> Result<Self::Input, ParseError> {
+        let points: Vec<Point> = parse_lines(input)?;
+        if points.is_empty() {
+            return Err(ParseError::new(
+                input,
+                "points like position=< 9,  1> velocity=< 0,  2>",
+            ));
+        }
+        Ok(points)
+    }
+
+    fn part1(&self, input: Self::Input) -> Result<Answer, Error> {
+        solve1(input, Path::new(IMAGE))?;
+        Ok(Answer::Image(IMAGE.to_string()))
+    }
+
+    fn part2(&self, input: Self::Input) -> Result<Option<Answer>, Error> {
+        Ok(Some(solve2(input).into()))
+    }
 }
 
 #[cfg(test)]
@@ -130,45 +162,11 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test1() {
-        let input = [
-            "position=< 9,  1> velocity=< 0,  2>",
-            "position=< 7,  0> velocity=<-1,  0>",
-            "position=< 3, -2> velocity=<-1,  1>",
-            "position=< 6, 10> velocity=<-2, -1>",
-            "position=< 2, -4> velocity=< 2,  2>",
-            "position=<-6, 10> velocity=< 2, -2>",
-            "position=< 1,  8> velocity=< 1, -1>",
-            "position=< 1,  7> velocity=< 1,  0>",
-            "position=<-3, 11> velocity=< 1, -2>",
-            "position=< 7,  6> velocity=<-1, -1>",
-            "position=<-2,  3> velocity=< 1,  0>",
-            "position=<-4,  3> velocity=< 2,  0>",
-            "position=<10, -3> velocity=<-1,  1>",
-            "position=< 5, 11> velocity=< 1, -2>",
-            "position=< 4,  7> velocity=< 0, -1>",
-            "position=< 8, -2> velocity=< 0,  1>",
-            "position=<15,  0> velocity=<-2,  0>",
-            "position=< 1,  6> velocity=< 1,  0>",
-            "position=< 8,  9> velocity=< 0, -1>",
-            "position=< 3,  3> velocity=<-1,  1>",
-            "position=< 0,  5> velocity=< 0, -1>",
-            "position=<-2,  2> velocity=< 2,  0>",
-            "position=< 5, -2> velocity=< 1,  2>",
-            "position=< 1,  4> velocity=< 2,  1>",
-            "position=<-2,  7> velocity=< 2, -2>",
-            "position=< 3,  6> velocity=<-1, -1>",
-            "position=< 5,  0> velocity=< 1,  0>",
-            "position=<-6,  0> velocity=< 2,  0>",
-            "position=< 5,  9> velocity=< 1, -2>",
-            "position=<14,  7> velocity=<-2,  0>",
-            "position=<-3,  6> velocity=< 2, -1>",
-        ]
-        .iter()
-        .map(|&s| String::from(s).parse::<Point>().unwrap())
-        .collect();
-
-        solve1(input, 50, 16);
-        assert!(true);
+    fn test_parse() {
+        assert!(Day10.parse("").is_err());
+        let points = Day10
+            .parse("position=< 1,  2> velocity=< 0,  0>\nposition=< 3,  4> velocity=< 0,  0>")
+            .unwrap();
+        assert_eq!(solve2(points), 0);
     }
 }
@@ -1,3 +1,9 @@
+#![allow(clippy::map_flatten, clippy::needless_range_loop)]
+
+use crate::answer::Answer;
+use crate::error::{parse_as, Error, ParseError};
+use crate::solution::Solution;
+
 fn power_level(x: i64, y: i64, serial: i64) -> i64 {
     let rack_id = x + 10;
     let a = (rack_id * y + serial) * rack_id;
@@ -55,6 +61,25 @@ pub fn solve2(serial: i64) -> (i64, i64, i64) {
     (x, y, size)
 }
 
+pub struct Day11;
+
+impl Solution for Day11 {
+    // Grid serial number.
+    type Input = i64;
+
+    fn parse(&self, input: &str) -> Result<Self::Input, ParseError> {
+        parse_as(input.trim(), "grid serial number")
+    }
+
+    fn part1(&self, serial: Self::Input) -> Result<Answer, Error> {
+        Ok(solve1(serial).into())
+    }
+
+    fn part2(&self, serial: Self::Input) -> Result<Option<Answer>, Error> {
+        Ok(Some(solve2(serial).into()))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -66,15 +91,4 @@ mod tests {
         assert_eq!(power_level(217, 196, 39), 0);
         assert_eq!(power_level(101, 153, 71), 4);
     }
-
-    #[test]
-    fn test1() {
-        assert_eq!(solve1(18), (33, 45));
-        assert_eq!(solve1(42), (21, 61));
-    }
-
-    #[test]
-    fn test2() {
-        // assert_eq!(solve2(18), (90, 269, 16));
-    }
 }
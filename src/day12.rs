@@ -1,51 +1,68 @@
+#![allow(clippy::bool_comparison, clippy::ptr_arg, clippy::single_match)]
+
 use std::str::FromStr;
 
-#[derive(Debug)]
+use crate::answer::Answer;
+use crate::error::{Error, ParseError};
+use crate::solution::Solution;
+
+#[derive(Debug, Clone)]
 struct Rule {
     rule: Vec<bool>,
     result: bool,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Pots {
     generations: Vec<Vec<bool>>,
     rules: Vec<Rule>,
 }
 
-fn parse_plant(c: char) -> bool {
-    match c {
-        '#' => true,
-        '.' => false,
-        _ => panic!("unexpected char"),
-    }
+// Parse pots of a line starting from column.
+fn parse_plants(l: &str, column: usize) -> Result<Vec<bool>, ParseError> {
+    l.chars()
+        .enumerate()
+        .map(|(i, c)| match c {
+            '#' => Ok(true),
+            '.' => Ok(false),
+            _ => Err(ParseError::new(l, "'#' or '.'").at_column(column + i)),
+        })
+        .collect()
 }
 
 const OFFSET: usize = 500;
 
 impl FromStr for Pots {
-    type Err = Box<::std::error::Error>;
+    type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut initial: Vec<bool> = s
-            .lines()
-            .nth(0)
-            .and_then(|l| l.split("state: ").nth(1))
-            .map(|l| l.trim().chars().map(parse_plant).collect())
-            .unwrap();
+        const PREFIX: &str = "initial state: ";
+        let first = s.lines().next().unwrap_or("");
+        if !first.starts_with(PREFIX) {
+            return Err(ParseError::new(first, "initial state: #..#.#..##").at(1, 1));
+        }
+        let mut initial = parse_plants(first[PREFIX.len()..].trim_end(), PREFIX.len() + 1)
+            .map_err(|e| e.offset(0))?;
 
         let rules = s
             .lines()
+            .enumerate()
             .skip(2)
-            .map(|l| {
+            .map(|(i, l)| {
+                let err = || ParseError::new(l, "rule like ...## => #").offset(i);
                 let mut it = l.trim().split(" => ");
-                let rule = it.next().unwrap().chars().map(parse_plant).collect();
-                let result = it.next().unwrap().chars().map(parse_plant).nth(0).unwrap();
-                Rule {
-                    rule: rule,
-                    result: result,
+                let rule = parse_plants(it.next().ok_or_else(err)?, 1).map_err(|e| e.offset(i))?;
+                let result =
+                    parse_plants(it.next().ok_or_else(err)?, 10).map_err(|e| e.offset(i))?;
+                if rule.len() != 5 || result.len() != 1 {
+                    return Err(err());
                 }
+                Ok(Rule {
+                    rule,
+                    result: result[0],
+                })
             })
-            .collect();
+            .collect::<Result<_, _>>()?;
 
         let mut pots = vec![false; OFFSET];
         pots.append(&mut initial);
@@ -90,7 +107,7 @@ impl Pots {
 
     #[allow(dead_code)]
     fn print_pots(&self) {
-        println!(
+        debug!(
             "{} {}",
             self.generations[self.generations.len() - 1]
                 .iter()
@@ -131,8 +148,7 @@ impl Pots {
     }
 }
 
-pub fn solve1(s: String) -> i64 {
-    let mut pots: Pots = s.parse().unwrap();
+pub fn solve1(mut pots: Pots) -> i64 {
     // pots.print_pots();
     for _ in 0..20 {
         pots.next_generation();
@@ -142,8 +158,7 @@ pub fn solve1(s: String) -> i64 {
     pots.sum_pots(pots.generations.len() - 1)
 }
 
-pub fn solve2(s: String) -> usize {
-    let mut pots: Pots = s.parse().unwrap();
+pub fn solve2(mut pots: Pots) -> usize {
     let mut gen = 0;
     let matching_generation = loop {
         pots.next_generation();
@@ -162,29 +177,20 @@ pub fn solve2(s: String) -> usize {
     (rounds - gen) * diff + sum
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test_solve1() {
-        let input = "initial state: #..#.#..##......###...###
-
-...## => #
-..#.. => #
-.#... => #
-.#.#. => #
-.#.## => #
-.##.. => #
-.#### => #
-#.#.# => #
-#.### => #
-##.#. => #
-##.## => #
-###.. => #
-###.# => #
-####. => #";
-
-        assert_eq!(solve1(input.to_string()), 325);
+pub struct Day12;
+
+impl Solution for Day12 {
+    type Input = Pots;
+
+    fn parse(&self, input: &str) -> Result<Self::Input, ParseError> {
+        input.parse()
+    }
+
+    fn part1(&self, input: Self::Input) -> Result<Answer, Error> {
+        Ok(solve1(input).into())
+    }
+
+    fn part2(&self, input: Self::Input) -> Result<Option<Answer>, Error> {
+        Ok(Some(solve2(input).into()))
     }
 }
@@ -1,6 +1,17 @@
+#![allow(
+    clippy::bool_comparison,
+    clippy::map_flatten,
+    clippy::needless_bool,
+    clippy::redundant_field_names
+)]
+
 use std::cell::RefCell;
 use std::str::FromStr;
 
+use crate::answer::Answer;
+use crate::error::{Error, ParseError};
+use crate::solution::Solution;
+
 #[derive(Debug, Clone)]
 enum CartTurn {
     Left,
@@ -59,13 +70,13 @@ impl Cart {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct Track {
     track: char,
     cart: Option<Cart>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Tracks {
     tracks: Vec<Vec<Option<RefCell<Track>>>>,
     cur_tick: bool,
@@ -73,7 +84,7 @@ pub struct Tracks {
 }
 
 // Cart on intersection is not parsed correctly, but input seems not to contain them.
-fn parse_track(c: char) -> Option<RefCell<Track>> {
+fn parse_track(c: char) -> Result<Option<RefCell<Track>>, char> {
     let (cart, track) = match c {
         '/' => (None, c),
         '\\' => (None, c),
@@ -84,28 +95,40 @@ fn parse_track(c: char) -> Option<RefCell<Track>> {
         '<' => (Some(Direction::Left), '-'),
         '^' => (Some(Direction::Up), '|'),
         'v' => (Some(Direction::Down), '|'),
-        ' ' => return None,
-        _ => panic!("unexpected char: {}", c),
+        ' ' => return Ok(None),
+        _ => return Err(c),
     };
 
-    Some(RefCell::new(Track {
+    Ok(Some(RefCell::new(Track {
         track,
         cart: cart.map(|c| Cart {
             c: c,
             turn: CartTurn::Left,
             tick: true,
         }),
-    }))
+    })))
 }
 
 impl FromStr for Tracks {
-    type Err = Box<::std::error::Error>;
+    type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let initial: Vec<Vec<Option<RefCell<Track>>>> = s
             .lines()
-            .map(|l| l.trim_end_matches('\n').chars().map(parse_track).collect())
-            .collect();
+            .enumerate()
+            .map(|(y, l)| {
+                l.trim_end_matches('\n')
+                    .chars()
+                    .enumerate()
+                    .map(|(x, c)| {
+                        parse_track(c).map_err(|_| {
+                            ParseError::new(l, "track (/\\+-| or space) or cart (<>^v)")
+                                .at(y + 1, x + 1)
+                        })
+                    })
+                    .collect()
+            })
+            .collect::<Result<_, _>>()?;
 
         Ok(Tracks {
             tracks: initial,
@@ -239,8 +262,7 @@ impl Tracks {
     }
 }
 
-pub fn solve1(s: String) -> (usize, usize) {
-    let mut tracks: Tracks = s.parse().unwrap();
+pub fn solve1(mut tracks: Tracks) -> (usize, usize) {
     loop {
         // println!("{}", tracks.print_track());
         if let Some(crash) = tracks.tick() {
@@ -250,8 +272,7 @@ pub fn solve1(s: String) -> (usize, usize) {
     }
 }
 
-pub fn solve2(s: String) -> (usize, usize) {
-    let mut tracks: Tracks = s.parse().unwrap();
+pub fn solve2(mut tracks: Tracks) -> (usize, usize) {
     tracks.remove_on_collision = true;
     loop {
         if let Some(last_cart) = tracks.tick() {
@@ -260,32 +281,20 @@ pub fn solve2(s: String) -> (usize, usize) {
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+pub struct Day13;
 
-    #[test]
-    fn test_solve1() {
-        let input = r"/->-\        
-|   |  /----\
-| /-+--+-\  |
-| | |  | v  |
-\-+-/  \-+--/
-  \------/   ";
+impl Solution for Day13 {
+    type Input = Tracks;
 
-        assert_eq!(solve1(input.to_string()), (7, 3));
+    fn parse(&self, input: &str) -> Result<Self::Input, ParseError> {
+        input.parse()
     }
 
-    #[test]
-    fn test_solve2() {
-        let input = r"/>-<\  
-|   |  
-| /<+-\
-| | | v
-\>+</ |
-  |   ^
-  \<->/";
+    fn part1(&self, input: Self::Input) -> Result<Answer, Error> {
+        Ok(solve1(input).into())
+    }
 
-        assert_eq!(solve2(input.to_string()), (6, 4));
+    fn part2(&self, input: Self::Input) -> Result<Option<Answer>, Error> {
+        Ok(Some(solve2(input).into()))
     }
 }
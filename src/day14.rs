@@ -1,5 +1,11 @@
+#![allow(clippy::unnecessary_cast)]
+
 use std::fmt;
 
+use crate::answer::Answer;
+use crate::error::{parse_as, Error, ParseError};
+use crate::solution::Solution;
+
 fn to_digits(mut x: i64) -> Vec<i64> {
     if x == 0 {
         return vec![0];
@@ -96,6 +102,30 @@ pub fn solve2(scores: &str) -> usize {
     }
 }
 
+pub struct Day14;
+
+impl Solution for Day14 {
+    // (number of recipes, its digits), leading zeros matter for part 2.
+    type Input = (usize, String);
+
+    fn parse(&self, input: &str) -> Result<Self::Input, ParseError> {
+        const EXPECTED: &str = "number of recipes like 765071";
+        let input = input.trim();
+        if input.is_empty() || !input.chars().all(|c| c.is_ascii_digit()) {
+            return Err(ParseError::new(input, EXPECTED));
+        }
+        Ok((parse_as(input, EXPECTED)?, input.to_string()))
+    }
+
+    fn part1(&self, (n_recipes, _): Self::Input) -> Result<Answer, Error> {
+        Ok(solve1(n_recipes).into())
+    }
+
+    fn part2(&self, (_, digits): Self::Input) -> Result<Option<Answer>, Error> {
+        Ok(Some(solve2(&digits).into()))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -109,18 +139,9 @@ mod tests {
     }
 
     #[test]
-    fn test1() {
-        assert_eq!(solve1(9), "5158916779");
-        assert_eq!(solve1(5), "0124515891");
-        assert_eq!(solve1(18), "9251071085");
-        assert_eq!(solve1(2018), "5941429882");
-    }
-
-    #[test]
-    fn test2() {
-        assert_eq!(solve2("51589"), 9);
-        assert_eq!(solve2("01245"), 5);
-        assert_eq!(solve2("92510"), 18);
-        assert_eq!(solve2("59414"), 2018);
+    fn test_parse() {
+        assert_eq!(Day14.parse("01245\n"), Ok((1245, "01245".to_string())));
+        assert!(Day14.parse("99999999999999999999999").is_err());
+        assert!(Day14.parse("12a").is_err());
     }
 }
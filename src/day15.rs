@@ -1,7 +1,23 @@
+#![allow(
+    clippy::bool_comparison,
+    clippy::get_first,
+    clippy::int_plus_one,
+    clippy::legacy_numeric_constants,
+    clippy::len_zero,
+    clippy::map_flatten,
+    clippy::redundant_field_names,
+    clippy::unnecessary_cast,
+    clippy::write_with_newline
+)]
+
 use std::collections::{HashMap, HashSet, VecDeque};
 use std::fmt;
 use std::str::FromStr;
 
+use crate::answer::Answer;
+use crate::error::{Error, ParseError};
+use crate::solution::Solution;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 enum UnitType {
     Goblin,
@@ -41,26 +57,26 @@ impl Pos {
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 enum Tile {
     Wall,
     Open,
 }
 
-#[derive(Debug)]
-struct Map {
+#[derive(Debug, Clone)]
+pub struct Map {
     tiles: Vec<Vec<Tile>>,
     units: Vec<Unit>,
     end_on_elf_death: bool,
 }
 
-fn parse_tile(c: char, pos: Pos) -> (Tile, Option<Unit>) {
+fn parse_tile(c: char, pos: Pos) -> Option<(Tile, Option<Unit>)> {
     match c {
-        '#' => (Tile::Wall, None),
-        '.' => (Tile::Open, None),
-        'G' => (Tile::Open, Some(Unit::new(UnitType::Goblin, pos))),
-        'E' => (Tile::Open, Some(Unit::new(UnitType::Elf, pos))),
-        _ => panic!("unexpected char: {}", c),
+        '#' => Some((Tile::Wall, None)),
+        '.' => Some((Tile::Open, None)),
+        'G' => Some((Tile::Open, Some(Unit::new(UnitType::Goblin, pos)))),
+        'E' => Some((Tile::Open, Some(Unit::new(UnitType::Elf, pos)))),
+        _ => None,
     }
 }
 
@@ -79,23 +95,23 @@ enum EndReason {
 }
 
 impl FromStr for Map {
-    type Err = Box<::std::error::Error>;
+    type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let initial = s.lines().enumerate().map(|(y, l)| {
-            l.trim_end_matches('\n')
-                .chars()
-                .enumerate()
-                .map(move |(x, c)| parse_tile(c, Pos::new(x, y)))
-        });
-
-        let units = initial
-            .clone()
-            .flatten()
-            .map(|(_, u)| u)
-            .flatten()
-            .collect();
-        let tiles = initial.map(|l| l.map(|(t, _)| t).collect()).collect();
+        let mut tiles = vec![];
+        let mut units = vec![];
+        for (y, l) in s.lines().enumerate() {
+            let mut row = vec![];
+            for (x, c) in l.trim_end_matches('\n').chars().enumerate() {
+                let (tile, unit) = parse_tile(c, Pos::new(x, y)).ok_or_else(|| {
+                    ParseError::new(l, "wall (#), open cavern (.), goblin (G) or elf (E)")
+                        .at(y + 1, x + 1)
+                })?;
+                row.push(tile);
+                units.extend(unit);
+            }
+            tiles.push(row);
+        }
         Ok(Map {
             tiles,
             units,
@@ -271,8 +287,7 @@ impl Map {
     }
 }
 
-pub fn solve1(s: String) -> i64 {
-    let mut map: Map = s.parse().unwrap();
+pub fn solve1(mut map: Map) -> i64 {
     let mut turns: i64 = 0;
 
     while map.advance_turn() == EndReason::Continue {
@@ -285,13 +300,12 @@ pub fn solve1(s: String) -> i64 {
         .filter(|u| u.removed == false)
         .map(|u| u.hp as i64)
         .sum();
-    println!("turns: {}, hp: {}", turns, hit_points);
+    info!("turns: {}, hp: {}", turns, hit_points);
 
     turns * hit_points
 }
 
-pub fn solve2(s: String) -> i64 {
-    let mut map: Map = s.parse().unwrap();
+pub fn solve2(mut map: Map) -> i64 {
     map.end_on_elf_death = true;
     let units_on_start = map.units.clone();
     let mut reason = EndReason::Continue;
@@ -317,7 +331,7 @@ pub fn solve2(s: String) -> i64 {
         }
         // Go back to last full round.
         turns -= 1;
-        println!("attack: {} ", 3 + iterations);
+        debug!("attack: {}", 3 + iterations);
         iterations += 1;
     }
 
@@ -328,111 +342,24 @@ pub fn solve2(s: String) -> i64 {
         .map(|u| u.hp as i64)
         .sum();
 
-    println!("turns: {}, hp: {}", turns, hit_points);
+    info!("turns: {}, hp: {}", turns, hit_points);
     turns * hit_points
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test_solve1_1() {
-        let input = r"#######
-#.G...#
-#...EG#
-#.#.#G#
-#..G#E#
-#.....#
-#######";
-        assert_eq!(solve1(input.to_string()), 27730);
-    }
+pub struct Day15;
 
-    #[test]
-    fn test_solve1_2() {
-        let input = r"#######
-#G..#E#
-#E#E.E#
-#G.##.#
-#...#E#
-#...E.#
-#######";
-        assert_eq!(solve1(input.to_string()), 36334);
-    }
-
-    #[test]
-    fn test_solve1_3() {
-        let input = r"#######
-#E..EG#
-#.#G.E#
-#E.##E#
-#G..#.#
-#..E#.#
-#######";
-        assert_eq!(solve1(input.to_string()), 39514);
-    }
-
-    #[test]
-    fn test_solve1_4() {
-        let input = r"#######
-#E.G#.#
-#.#G..#
-#G.#.G#
-#G..#.#
-#...E.#
-#######";
-        assert_eq!(solve1(input.to_string()), 27755);
-    }
-
-    #[test]
-    fn test_solve1_5() {
-        let input = r"#########
-#G......#
-#.E.#...#
-#..##..G#
-#...##..#
-#...#...#
-#.G...G.#
-#.....G.#
-#########";
-        assert_eq!(solve1(input.to_string()), 18740);
-    }
+impl Solution for Day15 {
+    type Input = Map;
 
-    #[test]
-    fn test_solve2_1() {
-        let input = r"#######
-#.G...#
-#...EG#
-#.#.#G#
-#..G#E#
-#.....#
-#######";
-        assert_eq!(solve2(input.to_string()), 4988);
+    fn parse(&self, input: &str) -> Result<Self::Input, ParseError> {
+        input.parse()
     }
 
-    #[test]
-    fn test_solve2_2() {
-        let input = r"#######
-#E..EG#
-#.#G.E#
-#E.##E#
-#G..#.#
-#..E#.#
-#######";
-        assert_eq!(solve2(input.to_string()), 31284);
+    fn part1(&self, input: Self::Input) -> Result<Answer, Error> {
+        Ok(solve1(input).into())
     }
 
-    #[test]
-    fn test_solve2_4() {
-        let input = r"#########
-#G......#
-#.E.#...#
-#..##..G#
-#...##..#
-#...#...#
-#.G...G.#
-#.....G.#
-#########";
-        assert_eq!(solve2(input.to_string()), 1140);
+    fn part2(&self, input: Self::Input) -> Result<Option<Answer>, Error> {
+        Ok(Some(solve2(input).into()))
     }
 }
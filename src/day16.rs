@@ -1,38 +1,14 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
 use std::str::FromStr;
 
-#[allow(non_camel_case_types)]
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
-enum OpCode {
-    addr,
-    addi,
-    mulr,
-    muli,
-    banr,
-    bani,
-    borr,
-    bori,
-    setr,
-    seti,
-    gtir,
-    gtri,
-    gtrr,
-    eqir,
-    eqri,
-    eqrr,
-}
-use self::OpCode::*;
-const ALL_OPCODES: &[OpCode] = &[
-    addr, addi, mulr, muli, banr, bani, borr, bori, setr, seti, gtir, gtri, gtrr, eqir, eqri, eqrr,
-];
-
-struct Instruction {
-    opcode: OpCode,
-    a: i64,
-    b: i64,
-    c: i64,
-}
+use crate::answer::Answer;
+use crate::elfcode::{Instruction, OpCode, VmError, VM};
+use crate::error::{Error, ParseError};
+use crate::inference::{matching_opcodes, Inference, InferenceError};
+use crate::solution::Solution;
 
+#[derive(Debug, Clone, PartialEq, Eq)]
 struct UnknownInstruction {
     opcode: i64,
     a: i64,
@@ -42,12 +18,20 @@ struct UnknownInstruction {
 
 type Registers = [i64; 4];
 
+#[derive(Debug, Clone, PartialEq, Eq)]
 struct Sample {
     registers_before: Registers,
     instruction: UnknownInstruction,
     registers_after: Registers,
 }
 
+// Samples and the test program.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Manual {
+    samples: Vec<Sample>,
+    program: Vec<UnknownInstruction>,
+}
+
 fn fill_slice<I: IntoIterator<Item = i64>>(iter: I) -> [i64; 4] {
     let mut r1 = [0; 4];
     for (ret, src) in r1.iter_mut().zip(iter) {
@@ -56,194 +40,257 @@ fn fill_slice<I: IntoIterator<Item = i64>>(iter: I) -> [i64; 4] {
     r1
 }
 
-impl FromStr for Sample {
-    type Err = Box<::std::error::Error>;
+// Registers line of sample, e.g. "Before: [3, 2, 1, 1]" with label "Before:".
+fn parse_registers(l: &str, label: &str) -> Result<Registers, ParseError> {
+    let expected = format!("registers like {} [3, 2, 1, 1]", label);
+    let err = || ParseError::new(l, &expected);
+    let values = l
+        .trim()
+        .strip_prefix(label)
+        .and_then(|v| v.trim().strip_prefix('['))
+        .and_then(|v| v.strip_suffix(']'))
+        .ok_or_else(err)?
+        .split(',')
+        .map(|d| d.trim().parse())
+        .collect::<Result<Vec<i64>, _>>()
+        .map_err(|_| err())?;
+    if values.len() != 4 {
+        return Err(err());
+    }
 
+    Ok(fill_slice(values))
+}
+
+impl FromStr for UnknownInstruction {
+    type Err = ParseError;
+
+    // input: "9 2 1 2"
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let parse_reg = |line| {
-            let prefix = "Before: [".len()..;
-            s.lines()
-                .nth(line)
-                .unwrap()
-                .trim()
-                .get(prefix.clone())
-                .unwrap()
-                .trim_end_matches(']')
-                .split(',')
-                .map(|d| d.trim().parse().unwrap())
-        };
-        let ins: Vec<i64> = s
-            .lines()
-            .nth(1)
-            .unwrap()
-            .trim()
-            .split(' ')
-            .map(|d| d.trim().parse().unwrap())
-            .collect();
+        let err = || ParseError::new(s, "instruction like 9 2 1 2");
+        let ins = s
+            .split_whitespace()
+            .map(|d| d.parse())
+            .collect::<Result<Vec<i64>, _>>()
+            .map_err(|_| err())?;
+        if ins.len() != 4 {
+            return Err(err());
+        }
 
-        Ok(Sample {
-            registers_before: fill_slice(parse_reg(0)),
-            instruction: UnknownInstruction {
-                opcode: ins[0],
-                a: ins[1],
-                b: ins[2],
-                c: ins[3],
-            },
-            registers_after: fill_slice(parse_reg(2)),
+        Ok(UnknownInstruction {
+            opcode: ins[0],
+            a: ins[1],
+            b: ins[2],
+            c: ins[3],
         })
     }
 }
 
-fn run(r: &mut Registers, ins: &Instruction) {
-    let a = ins.a as usize;
-    let b = ins.b as usize;
-    let c = ins.c as usize;
-
-    match ins.opcode {
-        addr => r[c] = r[a] + r[b],
-        addi => r[c] = r[a] + ins.b,
-        mulr => r[c] = r[a] * r[b],
-        muli => r[c] = r[a] * ins.b,
-        banr => r[c] = r[a] & r[b],
-        bani => r[c] = r[a] & ins.b,
-        borr => r[c] = r[a] | r[b],
-        bori => r[c] = r[a] | ins.b,
-        setr => r[c] = r[a],
-        seti => r[c] = ins.a,
-        gtir => r[c] = if ins.a > r[b] { 1 } else { 0 },
-        gtri => r[c] = if r[a] > ins.b { 1 } else { 0 },
-        gtrr => r[c] = if r[a] > r[b] { 1 } else { 0 },
-        eqir => r[c] = if ins.a == r[b] { 1 } else { 0 },
-        eqri => r[c] = if r[a] == ins.b { 1 } else { 0 },
-        eqrr => r[c] = if r[a] == r[b] { 1 } else { 0 },
+impl fmt::Display for UnknownInstruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {} {} {}", self.opcode, self.a, self.b, self.c)
     }
 }
 
-// Test if sample works with given opcode.
-fn test_sample(op: OpCode, sample: &Sample) -> bool {
-    let mut r: Registers = sample.registers_before.clone();
-    let ins = Instruction {
-        opcode: op,
-        a: sample.instruction.a,
-        b: sample.instruction.b,
-        c: sample.instruction.c,
-    };
-    run(&mut r, &ins);
+impl FromStr for Sample {
+    type Err = ParseError;
+
+    // input: "Before: [3, 2, 1, 1]\n9 2 1 2\nAfter:  [3, 2, 2, 1]"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lines: Vec<&str> = s.lines().collect();
+        let line = |i: usize| lines.get(i).cloned().unwrap_or("");
 
-    r == sample.registers_after
+        Ok(Sample {
+            registers_before: parse_registers(line(0), "Before:")?,
+            instruction: line(1).parse().map_err(|e: ParseError| e.offset(1))?,
+            registers_after: parse_registers(line(2), "After:").map_err(|e| e.offset(2))?,
+        })
+    }
 }
 
-pub fn solve1(s: String) -> i64 {
-    let samples_end = s.find("\n\n\n").unwrap();
-    let (samples_s, _) = s.split_at(samples_end);
-    let samples: Vec<Sample> = samples_s
-        .split("\n\n")
-        .map(|s| s.parse().unwrap())
-        .collect();
+impl FromStr for Manual {
+    type Err = ParseError;
 
-    let mut count = 0;
-    for sample in samples.iter() {
-        let matching: i64 = ALL_OPCODES
-            .iter()
-            .map(|op| if test_sample(*op, sample) { 1 } else { 0 })
-            .sum();
-        if matching >= 3 {
-            count += 1;
+    // Samples of three lines starting with "Before:", then the test program.
+    // Any number of blank lines may separate them, lines may end with CRLF.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lines: Vec<&str> = s.lines().map(|l| l.trim_end()).collect();
+        let blank = |i: usize| lines.get(i).is_some_and(|l| l.trim().is_empty());
+
+        let mut samples = vec![];
+        let mut i = 0;
+        loop {
+            while blank(i) {
+                i += 1;
+            }
+            match lines.get(i) {
+                Some(l) if l.trim_start().starts_with("Before:") => {}
+                _ => break,
+            }
+            let end = (i + 3).min(lines.len());
+            let sample = lines[i..end].join("\n");
+            samples.push(sample.parse().map_err(|e: ParseError| e.offset(i))?);
+            i = end;
         }
+
+        let program = lines[i..]
+            .iter()
+            .enumerate()
+            .filter(|(_, l)| !l.trim().is_empty())
+            .map(|(j, l)| l.parse().map_err(|e: ParseError| e.offset(i + j)))
+            .collect::<Result<_, _>>()?;
+
+        Ok(Manual { samples, program })
     }
+}
 
-    count
+impl Sample {
+    // Opcodes the sample behaves like.
+    fn matching(&self) -> BTreeSet<OpCode> {
+        let ins = &self.instruction;
+        matching_opcodes(
+            [ins.a, ins.b, ins.c],
+            &self.registers_before,
+            &self.registers_after,
+        )
+    }
 }
 
-pub fn solve2(s: String) -> i64 {
-    let samples_end = s.find("\n\n\n").unwrap();
-    let (samples_s, program_s) = s.split_at(samples_end);
-    let samples: Vec<Sample> = samples_s
-        .split("\n\n")
-        .map(|s| s.parse().unwrap())
-        .collect();
+pub fn solve1(manual: Manual) -> i64 {
+    manual
+        .samples
+        .iter()
+        .filter(|sample| sample.matching().len() >= 3)
+        .count() as i64
+}
 
-    let mut possible_opcodes: HashMap<i64, HashSet<OpCode>> = HashMap::new();
-    for sample in samples.iter() {
-        let opcode = sample.instruction.opcode;
-        let matching: HashSet<OpCode> = ALL_OPCODES
-            .iter()
-            .map(|&op| {
-                if test_sample(op, sample) {
-                    Some(op)
-                } else {
-                    None
-                }
+// Opcode of every number used in the samples.
+pub fn infer(manual: &Manual) -> Result<BTreeMap<i64, OpCode>, InferenceError> {
+    let mut inference = Inference::new();
+    for sample in manual.samples.iter() {
+        inference.observe(sample.instruction.opcode, &sample.matching());
+    }
+    debug!("possible opcodes: {:?}", inference.candidates);
+
+    inference.solve()
+}
+
+// Test program with opcode numbers replaced by the inferred opcodes.
+pub fn decode(manual: &Manual) -> Result<VM, Error> {
+    let mapping = infer(manual)?;
+    info!("opcode mapping: {:?}", mapping);
+
+    let program: Vec<Instruction> = manual
+        .program
+        .iter()
+        .map(|ins| {
+            let opcode = *mapping.get(&ins.opcode).ok_or_else(|| {
+                ParseError::new(&ins.to_string(), "opcode number used in a sample")
+            })?;
+            Ok(Instruction {
+                opcode,
+                a: ins.a,
+                b: ins.b,
+                c: ins.c,
             })
-            .flatten()
-            .collect();
+        })
+        .collect::<Result<_, ParseError>>()?;
 
-        let e = possible_opcodes.entry(opcode).or_insert(matching.clone());
-        let diff: Vec<_> = e.difference(&matching).map(|v| *v).collect();
-        for d in &diff {
-            e.remove(&d);
-        }
-    }
+    Ok(VM::new(4, None, program)?)
+}
 
-    println!("{:?}", possible_opcodes);
+// Register 0 after running the test program.
+pub fn solve2(mut vm: VM) -> Result<i64, VmError> {
+    // Without an instruction pointer register every instruction runs once.
+    vm.max_steps = Some(vm.program.len() as u64);
+    vm.run()?;
+    Ok(vm.registers[0])
+}
 
-    let mut mapping: HashMap<i64, OpCode> = HashMap::new();
-    while possible_opcodes.len() > 0 {
-        let found: Vec<_> = possible_opcodes
-            .iter()
-            .filter(|(_op, matching)| matching.len() == 1)
-            .map(|(op, matching)| (*op, *matching.iter().nth(0).unwrap()))
-            .collect();
-        for (op, matching) in found.iter() {
-            possible_opcodes.remove(op);
-            mapping.insert(*op, *matching);
-            for (_, values) in possible_opcodes.iter_mut() {
-                values.remove(matching);
-            }
-        }
-    }
+pub struct Day16;
 
-    println!("{:?}", mapping);
+impl Solution for Day16 {
+    type Input = Manual;
 
-    let program: Vec<Instruction> = program_s
-        .trim()
-        .lines()
-        .map(|l| {
-            let input: Vec<i64> = l
-                .trim()
-                .split(' ')
-                .map(|d| d.trim().parse().unwrap())
-                .collect();
-            Instruction {
-                opcode: *mapping.get(&input[0]).unwrap(),
-                a: input[1],
-                b: input[2],
-                c: input[3],
-            }
-        })
-        .collect();
+    fn parse(&self, input: &str) -> Result<Self::Input, ParseError> {
+        input.parse()
+    }
 
-    let mut registers: Registers = [0; 4];
-    for ins in &program {
-        run(&mut registers, ins);
+    fn part1(&self, input: Self::Input) -> Result<Answer, Error> {
+        Ok(solve1(input).into())
     }
 
-    registers[0]
+    // Manuals without a test program, like the examples, only have part 1.
+    fn part2(&self, input: Self::Input) -> Result<Option<Answer>, Error> {
+        if input.program.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(solve2(decode(&input)?)?.into()))
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    #[test]
-    fn test_solve1() {
-        let input = r"Before: [3, 2, 1, 1]
+    const MANUAL: &str = "Before: [3, 2, 1, 1]
 9 2 1 2
 After:  [3, 2, 2, 1]
 
+Before: [0, 1, 2, 3]
+4 0 1 3
+After:  [0, 1, 2, 1]
+
 
+
+9 0 1 2
+4 2 2 0
 ";
 
-        assert_eq!(solve1(input.to_string()), 1);
+    #[test]
+    fn test_parse() {
+        let manual: Manual = MANUAL.parse().unwrap();
+        assert_eq!(manual.samples.len(), 2);
+        assert_eq!(manual.samples[1].registers_after, [0, 1, 2, 1]);
+        assert_eq!(manual.program.len(), 2);
+        assert_eq!(solve1(manual.clone()), 2);
+
+        // CRLF, extra blank lines and other spacing.
+        let variant = MANUAL
+            .replace("After:  [", "After: [")
+            .replace("Before: [0, 1", "Before:[0,1")
+            .replace("\n\n", "\n\n\n")
+            .replace('\n', "\r\n");
+        assert_eq!(variant.parse(), Ok(manual.clone()));
+
+        // No blank lines at all before the program.
+        let tight = MANUAL.replace("\n\n\n\n", "\n");
+        assert_eq!(tight.parse(), Ok(manual));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let err = |s: &str| s.parse::<Manual>().unwrap_err();
+
+        let missing_after = MANUAL.replace("After:  [3, 2, 2, 1]", "");
+        let e = err(&missing_after);
+        assert_eq!((e.line, e.text.as_str()), (Some(3), ""));
+
+        let e = err(&MANUAL.replace("4 0 1 3", "4 0 1"));
+        assert_eq!(e.line, Some(6));
+        assert_eq!(e.expected, "instruction like 9 2 1 2");
+
+        let e = err(&MANUAL.replace("[0, 1, 2, 1]", "[0, 1, 2]"));
+        assert_eq!(e.line, Some(7));
+        assert_eq!(e.expected, "registers like After: [3, 2, 1, 1]");
+
+        assert_eq!(err(&MANUAL.replace("4 2 2 0", "4 2 x 0")).line, Some(12));
+
+        // Two samples can't decide the opcodes of the test program.
+        let e = Day16.part2(Day16.parse(MANUAL).unwrap()).unwrap_err();
+        assert!(e.to_string().starts_with("ambiguous samples"));
+        let manual = Day16
+            .parse(&MANUAL[..MANUAL.find("\n\n\n").unwrap()])
+            .unwrap();
+        assert!(Day16.part2(manual).unwrap().is_none());
     }
 }
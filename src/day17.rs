@@ -1,9 +1,16 @@
+#![allow(clippy::bool_comparison, clippy::write_with_newline)]
+
 use regex::Regex;
 use std::collections::{HashMap, VecDeque};
 use std::fmt;
 use std::ops::Range;
 use std::str::FromStr;
 
+use crate::answer::Answer;
+use crate::error::{captures, field, Error, ParseError};
+use crate::parse_lines;
+use crate::solution::Solution;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum Tile {
     Sand,
@@ -22,24 +29,26 @@ pub struct Vein {
 }
 
 impl FromStr for Vein {
-    type Err = Box<::std::error::Error>;
+    type Err = ParseError;
 
+    // input: x=495, y=2..7
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        const EXPECTED: &str = "vein like x=495, y=2..7";
         lazy_static! {
             static ref RE_X: Regex = Regex::new(r"x=(\d+)(?:\.\.(\d+))?").unwrap();
             static ref RE_Y: Regex = Regex::new(r"y=(\d+)(?:\.\.(\d+))?").unwrap();
         }
-        let cap_x = RE_X.captures(s).unwrap();
-        let cap_y = RE_Y.captures(s).unwrap();
-        let x_start: usize = cap_x[1].parse().unwrap();
-        let y_start: usize = cap_y[1].parse().unwrap();
-        let x_end: usize = if let Some(end) = cap_x.get(2) {
-            end.as_str().parse().unwrap()
+        let cap_x = captures(&RE_X, s, EXPECTED)?;
+        let cap_y = captures(&RE_Y, s, EXPECTED)?;
+        let x_start: usize = field(s, &cap_x, 1, EXPECTED)?;
+        let y_start: usize = field(s, &cap_y, 1, EXPECTED)?;
+        let x_end: usize = if cap_x.get(2).is_some() {
+            field(s, &cap_x, 2, EXPECTED)?
         } else {
             x_start
         };
-        let y_end: usize = if let Some(end) = cap_y.get(2) {
-            end.as_str().parse().unwrap()
+        let y_end: usize = if cap_y.get(2).is_some() {
+            field(s, &cap_y, 2, EXPECTED)?
         } else {
             y_start
         };
@@ -233,24 +242,20 @@ pub fn solve1(veins: Vec<Vein>) -> (i64, i64) {
     (count_all, count_still)
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+pub struct Day17;
+
+impl Solution for Day17 {
+    type Input = Vec<Vein>;
+
+    fn parse(&self, input: &str) -> Result<Self::Input, ParseError> {
+        parse_lines(input)
+    }
+
+    fn part1(&self, input: Self::Input) -> Result<Answer, Error> {
+        Ok(solve1(input).0.into())
+    }
 
-    #[test]
-    fn test_solve1_1() {
-        let input = r"
-x=495, y=2..7
-y=7, x=495..501
-x=501, y=3..7
-x=498, y=2..4
-x=506, y=1..2
-x=498, y=10..13
-x=504, y=10..13
-y=13, x=498..504";
-        assert_eq!(
-            solve1(input.trim().lines().map(|l| l.parse().unwrap()).collect()),
-            (57, 29)
-        );
+    fn part2(&self, input: Self::Input) -> Result<Option<Answer>, Error> {
+        Ok(Some(solve1(input).1.into()))
     }
 }
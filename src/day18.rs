@@ -1,6 +1,12 @@
+#![allow(clippy::write_with_newline)]
+
 use std::fmt;
 use std::str::FromStr;
 
+use crate::answer::Answer;
+use crate::error::{Error, ParseError};
+use crate::solution::Solution;
+
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
 enum Acre {
     OpenGround,
@@ -20,28 +26,40 @@ impl fmt::Display for Acre {
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
-struct Map {
+pub struct Map {
     acres: Vec<Vec<Acre>>,
 }
 
-fn parse_acre(c: char) -> Acre {
+fn parse_acre(c: char) -> Option<Acre> {
     match c {
-        '.' => Acre::OpenGround,
-        '|' => Acre::Trees,
-        '#' => Acre::Lumberyard,
-        _ => panic!("unexpected char: {}", c),
+        '.' => Some(Acre::OpenGround),
+        '|' => Some(Acre::Trees),
+        '#' => Some(Acre::Lumberyard),
+        _ => None,
     }
 }
 
 impl FromStr for Map {
-    type Err = Box<::std::error::Error>;
+    type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let acres = s
-            .trim()
             .lines()
-            .map(|l| l.trim().chars().map(parse_acre).collect())
-            .collect();
+            .enumerate()
+            .filter(|(_, l)| !l.trim().is_empty())
+            .map(|(y, l)| {
+                l.trim()
+                    .chars()
+                    .enumerate()
+                    .map(|(x, c)| {
+                        parse_acre(c).ok_or_else(|| {
+                            ParseError::new(l, "open ground (.), trees (|) or lumberyard (#)")
+                                .at(y + 1, x + 1)
+                        })
+                    })
+                    .collect()
+            })
+            .collect::<Result<_, _>>()?;
 
         Ok(Map { acres })
     }
@@ -130,9 +148,7 @@ impl Map {
     }
 }
 
-pub fn solve1(s: String) -> usize {
-    let mut map: Map = s.parse().unwrap();
-
+pub fn solve1(mut map: Map) -> usize {
     for _ in 0..10 {
         // println!("{}", map);
         map = map.next();
@@ -154,8 +170,7 @@ pub fn solve1(s: String) -> usize {
     trees * lumberyards
 }
 
-pub fn solve2(s: String) -> usize {
-    let mut map: Map = s.parse().unwrap();
+pub fn solve2(mut map: Map) -> usize {
     let mut maps: Vec<Map> = vec![];
 
     // Find cycle.
@@ -173,7 +188,7 @@ pub fn solve2(s: String) -> usize {
     // Corresponding index in prev_map..(prev_map + cycle).
     let cor = (1000000000 - prev_map) % cycle + prev_map - 1;
 
-    println!("cycle: {} cor: {}", cycle, cor);
+    info!("cycle: {} cor: {}", cycle, cor);
 
     let trees = maps[cor]
         .acres
@@ -191,22 +206,20 @@ pub fn solve2(s: String) -> usize {
     trees * lumberyards
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test_solve1_1() {
-        let input = r".#.#...|#.
-.....#|##|
-.|..|...#.
-..|#.....#
-#.#|||#|#|
-...#.||...
-.|....|...
-||...#|.#|
-|.||||..|.
-...#.|..|.";
-        assert_eq!(solve1(input.to_string()), 1147);
+pub struct Day18;
+
+impl Solution for Day18 {
+    type Input = Map;
+
+    fn parse(&self, input: &str) -> Result<Self::Input, ParseError> {
+        input.parse()
+    }
+
+    fn part1(&self, input: Self::Input) -> Result<Answer, Error> {
+        Ok(solve1(input).into())
+    }
+
+    fn part2(&self, input: Self::Input) -> Result<Option<Answer>, Error> {
+        Ok(Some(solve2(input).into()))
     }
 }
@@ -1,5 +1,9 @@
 use std::collections::HashSet;
 
+use crate::answer::Answer;
+use crate::error::{Error, ParseError};
+use crate::solution::Solution;
+
 // contains 2 and/or 3 same characters.
 // Returns (contains_two_same, contains_three_same).
 fn contains_n_same(s: &str) -> (bool, bool) {
@@ -67,31 +71,20 @@ pub fn solve2(ids: Vec<String>) -> String {
     panic!("no solution found");
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+pub struct Day2;
 
-    #[test]
-    fn test1() {
-        let ids = [
-            "abcdef", "bababc", "abbcde", "abcccd", "aabcdd", "abcdee", "ababab",
-        ]
-            .iter()
-            .map(|&s| String::from(s))
-            .collect();
+impl Solution for Day2 {
+    type Input = Vec<String>;
 
-        assert_eq!(solve1(ids), 12);
+    fn parse(&self, input: &str) -> Result<Self::Input, ParseError> {
+        Ok(input.lines().map(String::from).collect())
     }
 
-    #[test]
-    fn test2() {
-        let ids = [
-            "abcde", "fghij", "klmno", "pqrst", "fguij", "axcye", "wvxyz",
-        ]
-            .iter()
-            .map(|&s| String::from(s))
-            .collect();
+    fn part1(&self, input: Self::Input) -> Result<Answer, Error> {
+        Ok(solve1(input).into())
+    }
 
-        assert_eq!(solve2(ids), "fgij");
+    fn part2(&self, input: Self::Input) -> Result<Option<Answer>, Error> {
+        Ok(Some(solve2(input).into()))
     }
 }
@@ -1,4 +1,16 @@
+#![allow(
+    clippy::int_plus_one,
+    clippy::legacy_numeric_constants,
+    clippy::let_and_return,
+    clippy::map_clone
+)]
+
 use std::collections::{HashMap, HashSet, VecDeque};
+use std::str::FromStr;
+
+use crate::answer::Answer;
+use crate::error::{Error, ParseError};
+use crate::solution::Solution;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 struct Pos {
@@ -51,13 +63,33 @@ fn distances(doors: &HashSet<(Pos, Pos)>) -> HashMap<Pos, i64> {
     dist
 }
 
-fn parse_doors(s: String) -> HashSet<(Pos, Pos)> {
+// Doors between rooms of the facility.
+#[derive(Debug, Clone)]
+pub struct Facility {
+    doors: HashSet<(Pos, Pos)>,
+}
+
+impl FromStr for Facility {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let line = s.trim();
+        let start = s.len() - s.trim_start().len();
+        let doors = parse_doors(line)
+            .map_err(|(column, expected)| ParseError::new(line, expected).at(1, start + column))?;
+
+        Ok(Facility { doors })
+    }
+}
+
+// On error, return column and what was expected there.
+fn parse_doors(s: &str) -> Result<HashSet<(Pos, Pos)>, (usize, &'static str)> {
     let mut doors: HashSet<(Pos, Pos)> = HashSet::new();
     let mut branches: Vec<Pos> = vec![];
     let mut x = 0;
     let mut y = 0;
 
-    for c in s.trim().chars() {
+    for (i, c) in s.chars().enumerate() {
         match c {
             '^' => (),
             '$' => (),
@@ -81,64 +113,46 @@ fn parse_doors(s: String) -> HashSet<(Pos, Pos)> {
                 branches.push(Pos::new(x, y));
             }
             '|' => {
-                let pos = branches[branches.len() - 1];
+                let pos = branches.last().ok_or((i + 1, "'|' inside a branch"))?;
                 x = pos.x;
                 y = pos.y;
             }
             ')' => {
-                let r = branches.pop();
-                assert!(r.is_some());
+                branches.pop().ok_or((i + 1, "')' closing a branch"))?;
             }
-            _ => panic!("unexpected char {}", c),
+            _ => return Err((i + 1, "direction (NSEW), branch ((|)) or ^$")),
         }
     }
-    assert_eq!(branches.len(), 0);
-    doors
+    if !branches.is_empty() {
+        return Err((s.chars().count(), "')' closing every branch"));
+    }
+    Ok(doors)
 }
 
-pub fn solve1(s: String) -> i64 {
-    let doors = parse_doors(s);
-    let dist = distances(&doors);
+pub fn solve1(facility: Facility) -> i64 {
+    let dist = distances(&facility.doors);
     dist.values().max().map(|m| *m).unwrap()
 }
 
-pub fn solve2(s: String) -> i64 {
-    let doors = parse_doors(s);
-    let dist = distances(&doors);
+pub fn solve2(facility: Facility) -> i64 {
+    let dist = distances(&facility.doors);
     dist.values().filter(|&&distance| distance >= 1000).count() as i64
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+pub struct Day20;
 
-    #[test]
-    fn test_solve1_1() {
-        let input = r"^WNE$";
-        assert_eq!(solve1(input.to_string()), 3);
-    }
-
-    #[test]
-    fn test_solve1_2() {
-        let input = r"^ENWWW(NEEE|SSE(EE|N))$";
-        assert_eq!(solve1(input.to_string()), 10);
-    }
+impl Solution for Day20 {
+    type Input = Facility;
 
-    #[test]
-    fn test_solve1_3() {
-        let input = r"^ENNWSWW(NEWS|)SSSEEN(WNSE|)EE(SWEN|)NNN$";
-        assert_eq!(solve1(input.to_string()), 18);
+    fn parse(&self, input: &str) -> Result<Self::Input, ParseError> {
+        input.parse()
     }
 
-    #[test]
-    fn test_solve1_4() {
-        let input = r"^ESSWWN(E|NNENN(EESS(WNSE|)SSS|WWWSSSSE(SW|NNNE)))$";
-        assert_eq!(solve1(input.to_string()), 23);
+    fn part1(&self, input: Self::Input) -> Result<Answer, Error> {
+        Ok(solve1(input).into())
     }
 
-    #[test]
-    fn test_solve1_5() {
-        let input = r"^WSSEESWWWNW(S|NENNEEEENN(ESSSSW(NWSW|SSEN)|WSWWN(E|WWS(E|SS))))$";
-        assert_eq!(solve1(input.to_string()), 31);
+    fn part2(&self, input: Self::Input) -> Result<Option<Answer>, Error> {
+        Ok(Some(solve2(input).into()))
     }
 }
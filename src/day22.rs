@@ -1,7 +1,15 @@
+#![allow(clippy::map_flatten)]
+
 use std::cmp::Reverse;
 use std::collections::{BinaryHeap, HashMap};
 use std::fmt;
 
+use regex::Regex;
+
+use crate::answer::Answer;
+use crate::error::{captures, field, Error, ParseError};
+use crate::solution::Solution;
+
 #[derive(Debug, PartialEq, Eq, Hash)]
 struct Pos {
     y: usize,
@@ -227,17 +235,48 @@ pub fn solve2(depth: usize, target_x: usize, target_y: usize) -> i64 {
     *duration
 }
 
+pub struct Day22;
+
+impl Solution for Day22 {
+    // (depth, target x, target y)
+    type Input = (usize, usize, usize);
+
+    // input:
+    // depth: 7740
+    // target: 12,763
+    fn parse(&self, input: &str) -> Result<Self::Input, ParseError> {
+        const EXPECTED: &str = "cave like \"depth: 510\" and \"target: 10,10\" on separate lines";
+        lazy_static! {
+            static ref RE: Regex = Regex::new(r"^depth: (\d+)\s+target: (\d+),(\d+)$").unwrap();
+        }
+        let s = input.trim();
+        let cap = captures(&RE, s, EXPECTED)?;
+
+        Ok((
+            field(s, &cap, 1, EXPECTED)?,
+            field(s, &cap, 2, EXPECTED)?,
+            field(s, &cap, 3, EXPECTED)?,
+        ))
+    }
+
+    fn part1(&self, (depth, x, y): Self::Input) -> Result<Answer, Error> {
+        Ok(solve1(depth, x, y).into())
+    }
+
+    fn part2(&self, (depth, x, y): Self::Input) -> Result<Option<Answer>, Error> {
+        Ok(Some(solve2(depth, x, y).into()))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
-    fn test_solve1() {
-        assert_eq!(solve1(510, 10, 10), 114);
-    }
-
-    #[test]
-    fn test_solve2() {
-        assert_eq!(solve2(510, 10, 10), 45);
+    fn test_parse() {
+        assert_eq!(
+            Day22.parse("depth: 510\ntarget: 10,10\n"),
+            Ok((510, 10, 10))
+        );
     }
 }
@@ -1,6 +1,17 @@
+#![allow(
+    clippy::assign_op_pattern,
+    clippy::clone_on_copy,
+    clippy::legacy_numeric_constants
+)]
+
 use regex::Regex;
 use std::str::FromStr;
 
+use crate::answer::Answer;
+use crate::error::{captures, field, Error, ParseError};
+use crate::parse_lines;
+use crate::solution::Solution;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 struct Pos {
     z: i64,
@@ -33,22 +44,23 @@ pub struct Nanobot {
 }
 
 impl FromStr for Nanobot {
-    type Err = Box<::std::error::Error>;
+    type Err = ParseError;
 
     // pos=<-66538252,24214519,54774103>, r=94247941
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        const EXPECTED: &str = "nanobot like pos=<0,0,0>, r=4";
         lazy_static! {
             static ref RE: Regex = Regex::new(r"^pos=<(-?\d+),(-?\d+),(-?\d+)>, r=(\d+)$").unwrap();
         }
-        let cap = RE.captures(s).unwrap();
+        let cap = captures(&RE, s, EXPECTED)?;
 
         Ok(Nanobot {
             pos: Pos {
-                x: cap[1].parse().unwrap(),
-                y: cap[2].parse().unwrap(),
-                z: cap[3].parse().unwrap(),
+                x: field(s, &cap, 1, EXPECTED)?,
+                y: field(s, &cap, 2, EXPECTED)?,
+                z: field(s, &cap, 3, EXPECTED)?,
             },
-            radius: cap[4].parse().unwrap(),
+            radius: field(s, &cap, 4, EXPECTED)?,
         })
     }
 }
@@ -140,8 +152,8 @@ pub fn solve2(nanobots: Vec<Nanobot>) -> i64 {
             .min_by_key(|&(_, dist)| dist)
             .unwrap();
 
-        println!("max_in_range {:?}, equal: {:?}", max_in_range, equal.len());
-        println!("scale: {}, best: {:?}", scale, best);
+        debug!("max_in_range {:?}, equal: {:?}", max_in_range, equal.len());
+        debug!("scale: {}, best: {:?}", scale, best);
 
         if scale == 1 {
             return dist;
@@ -168,40 +180,20 @@ pub fn solve2(nanobots: Vec<Nanobot>) -> i64 {
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test_solve1() {
-        let input: Vec<Nanobot> = "pos=<0,0,0>, r=4
-pos=<1,0,0>, r=1
-pos=<4,0,0>, r=3
-pos=<0,2,0>, r=1
-pos=<0,5,0>, r=3
-pos=<0,0,3>, r=1
-pos=<1,1,1>, r=1
-pos=<1,1,2>, r=1
-pos=<1,3,1>, r=1"
-            .lines()
-            .map(|l| l.parse().unwrap())
-            .collect();
+pub struct Day23;
 
-        assert_eq!(solve1(input), 7);
+impl Solution for Day23 {
+    type Input = Vec<Nanobot>;
+
+    fn parse(&self, input: &str) -> Result<Self::Input, ParseError> {
+        parse_lines(input)
     }
 
-    #[test]
-    fn test_solve2() {
-        let input: Vec<Nanobot> = "pos=<10,12,12>, r=2
-pos=<12,14,12>, r=2
-pos=<16,12,12>, r=4
-pos=<14,14,14>, r=6
-pos=<50,50,50>, r=200
-pos=<10,10,10>, r=5"
-            .lines()
-            .map(|l| l.parse().unwrap())
-            .collect();
+    fn part1(&self, input: Self::Input) -> Result<Answer, Error> {
+        Ok(solve1(input).into())
+    }
 
-        assert_eq!(solve2(input), 36);
+    fn part2(&self, input: Self::Input) -> Result<Option<Answer>, Error> {
+        Ok(Some(solve2(input).into()))
     }
 }
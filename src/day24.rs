@@ -1,7 +1,13 @@
+#![allow(clippy::manual_contains, clippy::needless_borrow)]
+
 use regex::Regex;
 use std::collections::{HashMap, HashSet};
 use std::str::FromStr;
 
+use crate::answer::Answer;
+use crate::error::{captures, field, Error, ParseError};
+use crate::solution::Solution;
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 enum AttackType {
     Bludgeoning,
@@ -12,7 +18,7 @@ enum AttackType {
 }
 
 impl FromStr for AttackType {
-    type Err = Box<::std::error::Error>;
+    type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         Ok(match s {
@@ -21,7 +27,12 @@ impl FromStr for AttackType {
             "slashing" => AttackType::Slashing,
             "cold" => AttackType::Cold,
             "radiation" => AttackType::Radiation,
-            _ => panic!("unknown attack type: {}", s),
+            _ => {
+                return Err(ParseError::new(
+                    s,
+                    "bludgeoning, fire, slashing, cold or radiation",
+                ))
+            }
         })
     }
 }
@@ -33,7 +44,7 @@ enum Army {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
-struct Group {
+pub struct Group {
     hit_points: i64,
     attack_damage: i64,
     attack_type: AttackType,
@@ -56,29 +67,38 @@ impl Group {
 }
 
 impl FromStr for Group {
-    type Err = Box<::std::error::Error>;
+    type Err = ParseError;
 
     // this parses only immmunities first, then weaknesses,
     // so if they are in opposite order in input, they must be swapped manually.
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        const EXPECTED: &str = "group like \"17 units each with 5390 hit points (immune to fire; weak to radiation) with an attack that does 4507 fire damage at initiative 2\"";
         const RE_S: &str = r"^(\d+) units each with (\d+) hit points(?: \((?:immune to ((?:\w|,| )+))?(?:; )?(?:weak to ((?:\w|,| )+))?\))? with an attack that does (\d+) (\w+) damage at initiative (\d+)$";
         lazy_static! {
             static ref RE: Regex = Regex::new(RE_S).unwrap();
         }
 
-        let cap = RE.captures(s).unwrap();
+        let cap = captures(&RE, s, EXPECTED)?;
         let attack_types = |capt: Option<regex::Match>| match capt {
-            None => vec![],
-            Some(s) => s.as_str().split(", ").map(|a| a.parse().unwrap()).collect(),
+            None => Ok(vec![]),
+            Some(m) => m
+                .as_str()
+                .split(", ")
+                .map(|a| {
+                    a.parse().map_err(|e: ParseError| {
+                        ParseError::new(s, &e.expected).at_column(m.start() + 1)
+                    })
+                })
+                .collect(),
         };
         Ok(Group {
-            units: cap[1].parse()?,
-            hit_points: cap[2].parse()?,
-            immunities: attack_types(cap.get(3)),
-            weaknesses: attack_types(cap.get(4)),
-            attack_damage: cap[5].parse()?,
-            attack_type: cap[6].parse()?,
-            initiative: cap[7].parse()?,
+            units: field(s, &cap, 1, EXPECTED)?,
+            hit_points: field(s, &cap, 2, EXPECTED)?,
+            immunities: attack_types(cap.get(3))?,
+            weaknesses: attack_types(cap.get(4))?,
+            attack_damage: field(s, &cap, 5, EXPECTED)?,
+            attack_type: field(s, &cap, 6, "bludgeoning, fire, slashing, cold or radiation")?,
+            initiative: field(s, &cap, 7, EXPECTED)?,
             army: Army::ImmuneSystem, // Update later.
         })
     }
@@ -125,30 +145,26 @@ fn select_target<'a>(
         })
 }
 
-fn parse_groups(s: String) -> Vec<Group> {
-    let immune_system = s
-        .trim()
-        .lines()
-        .skip(1)
-        .take_while(|l| l.trim().len() > 0)
-        .map(|l| l.parse().unwrap())
-        .map(|mut g: Group| {
-            g.army = Army::ImmuneSystem;
-            g
-        });
+pub fn parse_groups(s: &str) -> Result<Vec<Group>, ParseError> {
+    let mut groups = vec![];
+    let mut army = None;
 
-    let infection = s
-        .trim()
-        .lines()
-        .skip_while(|l| l.trim().len() > 0)
-        .skip(2)
-        .map(|l| l.parse().unwrap())
-        .map(|mut g: Group| {
-            g.army = Army::Infection;
-            g
-        });
+    for (i, l) in s.lines().enumerate() {
+        match l.trim() {
+            "" => (),
+            "Immune System:" => army = Some(Army::ImmuneSystem),
+            "Infection:" => army = Some(Army::Infection),
+            l => {
+                let mut g: Group = l.parse().map_err(|e: ParseError| e.offset(i))?;
+                g.army = army
+                    .clone()
+                    .ok_or_else(|| ParseError::new(l, "army header before groups").offset(i))?;
+                groups.push(g);
+            }
+        }
+    }
 
-    immune_system.chain(infection).collect()
+    Ok(groups)
 }
 
 fn combat(groups: &mut Vec<Group>) {
@@ -227,18 +243,16 @@ fn combat(groups: &mut Vec<Group>) {
     }
 }
 
-pub fn solve1(s: String) -> i64 {
-    let mut groups: Vec<Group> = parse_groups(s);
+pub fn solve1(mut groups: Vec<Group>) -> i64 {
     combat(&mut groups);
     groups.iter().map(|g| g.units).sum()
 }
 
-pub fn solve2(s: String) -> i64 {
-    let groups: Vec<Group> = parse_groups(s);
+pub fn solve2(groups: Vec<Group>) -> i64 {
     // 0-34 infection wins, 35 seems to be stalemate (runs forever), so skip to 36.
     let mut boost = 36;
     loop {
-        println!("boost {}", boost);
+        debug!("boost {}", boost);
         let mut boosted: Vec<Group> = groups
             .iter()
             .map(|g| {
@@ -257,35 +271,20 @@ pub fn solve2(s: String) -> i64 {
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test_solve1() {
-        let input = "Immune System:
-17 units each with 5390 hit points (weak to radiation, bludgeoning) with an attack that does 4507 fire damage at initiative 2
-989 units each with 1274 hit points (immune to fire; weak to bludgeoning, slashing) with an attack that does 25 slashing damage at initiative 3
+pub struct Day24;
 
-Infection:
-801 units each with 4706 hit points (weak to radiation) with an attack that does 116 bludgeoning damage at initiative 1
-4485 units each with 2961 hit points (immune to radiation; weak to fire, cold) with an attack that does 12 slashing damage at initiative 4"
-            .to_string();
+impl Solution for Day24 {
+    type Input = Vec<Group>;
 
-        assert_eq!(solve1(input), 5216);
+    fn parse(&self, input: &str) -> Result<Self::Input, ParseError> {
+        parse_groups(input)
     }
 
-    #[test]
-    fn test_solve2() {
-        let input = "Immune System:
-17 units each with 5390 hit points (weak to radiation, bludgeoning) with an attack that does 4507 fire damage at initiative 2
-989 units each with 1274 hit points (immune to fire; weak to bludgeoning, slashing) with an attack that does 25 slashing damage at initiative 3
-
-Infection:
-801 units each with 4706 hit points (weak to radiation) with an attack that does 116 bludgeoning damage at initiative 1
-4485 units each with 2961 hit points (immune to radiation; weak to fire, cold) with an attack that does 12 slashing damage at initiative 4"
-            .to_string();
+    fn part1(&self, input: Self::Input) -> Result<Answer, Error> {
+        Ok(solve1(input).into())
+    }
 
-        assert_eq!(solve2(input), 51);
+    fn part2(&self, input: Self::Input) -> Result<Option<Answer>, Error> {
+        Ok(Some(solve2(input).into()))
     }
 }
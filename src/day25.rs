@@ -1,6 +1,11 @@
 use regex::Regex;
 use std::str::FromStr;
 
+use crate::answer::Answer;
+use crate::error::{captures, field, Error, ParseError};
+use crate::parse_lines;
+use crate::solution::Solution;
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Pos {
     x: i64,
@@ -23,20 +28,22 @@ impl Pos {
 }
 
 impl FromStr for Pos {
-    type Err = Box<::std::error::Error>;
+    type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        const EXPECTED: &str = "point like 0,-1,2,3";
         lazy_static! {
             static ref RE: Regex = Regex::new(r"^(-?\d+),(-?\d+),(-?\d+),(-?\d+)$").unwrap();
         }
-        println!("{}", s);
-        let cap = RE.captures(s.trim()).unwrap();
+        trace!("{}", s);
+        let s = s.trim();
+        let cap = captures(&RE, s, EXPECTED)?;
 
         Ok(Pos {
-            x: cap[1].parse()?,
-            y: cap[2].parse()?,
-            z: cap[3].parse()?,
-            w: cap[4].parse()?,
+            x: field(s, &cap, 1, EXPECTED)?,
+            y: field(s, &cap, 2, EXPECTED)?,
+            z: field(s, &cap, 3, EXPECTED)?,
+            w: field(s, &cap, 4, EXPECTED)?,
         })
     }
 }
@@ -59,77 +66,16 @@ pub fn solve1(mut points: Vec<Pos>) -> usize {
     constellations.len()
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test_solve1_1() {
-        let input = r"0,0,0,0
- 3,0,0,0
- 0,3,0,0
- 0,0,3,0
- 0,0,0,3
- 0,0,0,6
- 9,0,0,0
-12,0,0,0";
-        assert_eq!(
-            solve1(input.trim().lines().map(|l| l.parse().unwrap()).collect()),
-            2
-        );
-    }
+pub struct Day25;
 
-    #[test]
-    fn test_solve1_2() {
-        let input = r"-1,2,2,0
-0,0,2,-2
-0,0,0,-2
--1,2,0,0
--2,-2,-2,2
-3,0,2,-1
--1,3,2,2
--1,0,-1,0
-0,2,1,-2
-3,0,0,0";
-        assert_eq!(
-            solve1(input.trim().lines().map(|l| l.parse().unwrap()).collect()),
-            4
-        );
-    }
+impl Solution for Day25 {
+    type Input = Vec<Pos>;
 
-    #[test]
-    fn test_solve1_3() {
-        let input = r"1,-1,0,1
-2,0,-1,0
-3,2,-1,0
-0,0,3,1
-0,0,-1,-1
-2,3,-2,0
--2,2,0,0
-2,-2,0,-1
-1,-1,0,-1
-3,2,0,2";
-        assert_eq!(
-            solve1(input.trim().lines().map(|l| l.parse().unwrap()).collect()),
-            3
-        );
+    fn parse(&self, input: &str) -> Result<Self::Input, ParseError> {
+        parse_lines(input)
     }
 
-    #[test]
-    fn test_solve1_4() {
-        let input = r"1,-1,-1,-2
--2,-2,0,1
-0,2,1,3
--2,3,-2,1
-0,2,3,-2
--1,-1,1,-2
-0,-2,-1,0
--2,2,3,-1
-1,2,2,0
--1,-2,0,-2";
-        assert_eq!(
-            solve1(input.trim().lines().map(|l| l.parse().unwrap()).collect()),
-            8
-        );
+    fn part1(&self, input: Self::Input) -> Result<Answer, Error> {
+        Ok(solve1(input).into())
     }
 }
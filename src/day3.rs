@@ -1,9 +1,16 @@
+#![allow(clippy::redundant_field_names)]
+
 use std::collections::HashSet;
 use std::str::FromStr;
 
 use regex::Regex;
 
-#[derive(Debug)]
+use crate::answer::Answer;
+use crate::error::{captures, field, Error, ParseError};
+use crate::parse_lines;
+use crate::solution::Solution;
+
+#[derive(Debug, Clone)]
 pub struct Claim {
     id: i64,
     // left: i64,
@@ -14,20 +21,21 @@ pub struct Claim {
 }
 
 impl FromStr for Claim {
-    type Err = Box<::std::error::Error>;
+    type Err = ParseError;
 
     // #1273 @ 134,911: 13x12
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        const EXPECTED: &str = "claim like #1 @ 1,3: 4x4";
         lazy_static! {
             static ref RE: Regex = Regex::new(r"#(\d+) @ (\d+),(\d+): (\d+)x(\d+)").unwrap();
         }
 
-        let cap = RE.captures(s).unwrap();
+        let cap = captures(&RE, s, EXPECTED)?;
 
-        let left: i64 = cap[2].parse()?;
-        let top: i64 = cap[3].parse()?;
-        let width: i64 = cap[4].parse()?;
-        let height: i64 = cap[5].parse()?;
+        let left: i64 = field(s, &cap, 2, EXPECTED)?;
+        let top: i64 = field(s, &cap, 3, EXPECTED)?;
+        let width: i64 = field(s, &cap, 4, EXPECTED)?;
+        let height: i64 = field(s, &cap, 5, EXPECTED)?;
 
         let mut areas = HashSet::new();
         for x in (left + 1)..=(left + width) {
@@ -37,7 +45,7 @@ impl FromStr for Claim {
         }
 
         Ok(Claim {
-            id: cap[1].parse()?,
+            id: field(s, &cap, 1, EXPECTED)?,
             // left: cap[2].parse()?,
             // top: cap[3].parse()?,
             // width: cap[4].parse()?,
@@ -81,27 +89,20 @@ pub fn solve2(claims: Vec<Claim>) -> i64 {
     panic!("no solution found!")
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+pub struct Day3;
 
-    #[test]
-    fn test1() {
-        let ids = ["#1 @ 1,3: 4x4", "#2 @ 3,1: 4x4", "#3 @ 5,5: 2x2"]
-            .iter()
-            .map(|&s| String::from(s).parse::<Claim>().unwrap())
-            .collect();
+impl Solution for Day3 {
+    type Input = Vec<Claim>;
 
-        assert_eq!(solve1(ids), 4);
+    fn parse(&self, input: &str) -> Result<Self::Input, ParseError> {
+        parse_lines(input)
     }
 
-    #[test]
-    fn test2() {
-        let ids = ["#1 @ 1,3: 4x4", "#2 @ 3,1: 4x4", "#3 @ 5,5: 2x2"]
-            .iter()
-            .map(|&s| String::from(s).parse::<Claim>().unwrap())
-            .collect();
+    fn part1(&self, input: Self::Input) -> Result<Answer, Error> {
+        Ok(solve1(input).into())
+    }
 
-        assert_eq!(solve2(ids), 3);
+    fn part2(&self, input: Self::Input) -> Result<Option<Answer>, Error> {
+        Ok(Some(solve2(input).into()))
     }
 }
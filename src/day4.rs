@@ -1,8 +1,19 @@
+#![allow(
+    clippy::map_entry,
+    clippy::needless_range_loop,
+    clippy::while_let_on_iterator
+)]
+
 use regex::Regex;
 
 use std::collections::HashMap;
 use std::str::FromStr;
 
+use crate::answer::Answer;
+use crate::error::{captures, field, Error, ParseError};
+use crate::parse_lines;
+use crate::solution::Solution;
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub enum EntryInfo {
     GuardStarts(u64),
@@ -11,9 +22,10 @@ pub enum EntryInfo {
 }
 
 impl FromStr for EntryInfo {
-    type Err = Box<::std::error::Error>;
+    type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        const EXPECTED: &str = "\"Guard #10 begins shift\", \"falls asleep\" or \"wakes up\"";
         lazy_static! {
             static ref RE: Regex = Regex::new(r"^Guard #(\d+) begins shift$").unwrap();
         }
@@ -21,8 +33,8 @@ impl FromStr for EntryInfo {
             "falls asleep" => Ok(EntryInfo::FallsAsleep),
             "wakes up" => Ok(EntryInfo::WakesUp),
             _ => {
-                let cap = RE.captures(s).unwrap();
-                Ok(EntryInfo::GuardStarts(cap[1].parse()?))
+                let cap = captures(&RE, s, EXPECTED)?;
+                Ok(EntryInfo::GuardStarts(field(s, &cap, 1, EXPECTED)?))
             }
         }
     }
@@ -44,26 +56,27 @@ pub struct Entry {
 }
 
 impl FromStr for Entry {
-    type Err = Box<::std::error::Error>;
+    type Err = ParseError;
 
     // [1518-06-08 00:36] wakes up
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        const EXPECTED: &str = "record like [1518-11-01 00:05] falls asleep";
         lazy_static! {
             static ref RE: Regex =
                 Regex::new(r"^\[(\d{4})\-(\d{2})\-(\d{2}) (\d{2}):(\d{2})\] (.+)$").unwrap();
         }
 
-        let cap = RE.captures(s).unwrap();
+        let cap = captures(&RE, s, EXPECTED)?;
 
         Ok(Entry {
             date: SimpleDate {
-                year: cap[1].parse()?,
-                month: cap[2].parse()?,
-                day: cap[3].parse()?,
+                year: field(s, &cap, 1, EXPECTED)?,
+                month: field(s, &cap, 2, EXPECTED)?,
+                day: field(s, &cap, 3, EXPECTED)?,
             },
-            hours: cap[4].parse()?,
-            minutes: cap[5].parse()?,
-            content: cap[6].parse()?,
+            hours: field(s, &cap, 4, EXPECTED)?,
+            minutes: field(s, &cap, 5, EXPECTED)?,
+            content: field(s, &cap, 6, EXPECTED)?,
         })
     }
 }
@@ -120,8 +133,8 @@ pub fn solve1(records: Vec<Entry>) -> u64 {
         .position(|v| v == max_asleep_minutes)
         .unwrap();
 
-    println!("guard {}, minutes {}", max_id, max_asleep_minutes);
-    println!("max_asleep_minute {}", max_asleep_minute);
+    info!("guard {}, minutes {}", max_id, max_asleep_minutes);
+    info!("max_asleep_minute {}", max_asleep_minute);
 
     *max_id * (max_asleep_minute as u64)
 }
@@ -145,63 +158,20 @@ pub fn solve2(records: Vec<Entry>) -> u64 {
     *guard_id * minute as u64
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test1() {
-        let records = [
-            "[1518-11-01 00:00] Guard #10 begins shift",
-            "[1518-11-01 00:05] falls asleep",
-            "[1518-11-01 00:25] wakes up",
-            "[1518-11-01 00:30] falls asleep",
-            "[1518-11-01 00:55] wakes up",
-            "[1518-11-01 23:58] Guard #99 begins shift",
-            "[1518-11-02 00:40] falls asleep",
-            "[1518-11-02 00:50] wakes up",
-            "[1518-11-03 00:05] Guard #10 begins shift",
-            "[1518-11-03 00:24] falls asleep",
-            "[1518-11-03 00:29] wakes up",
-            "[1518-11-04 00:02] Guard #99 begins shift",
-            "[1518-11-04 00:36] falls asleep",
-            "[1518-11-04 00:46] wakes up",
-            "[1518-11-05 00:03] Guard #99 begins shift",
-            "[1518-11-05 00:45] falls asleep",
-            "[1518-11-05 00:55] wakes up",
-        ]
-        .iter()
-        .map(|&s| String::from(s).parse::<Entry>().unwrap())
-        .collect();
+pub struct Day4;
 
-        assert_eq!(solve1(records), 240);
+impl Solution for Day4 {
+    type Input = Vec<Entry>;
+
+    fn parse(&self, input: &str) -> Result<Self::Input, ParseError> {
+        parse_lines(input)
     }
 
-    #[test]
-    fn test2() {
-        let records = [
-            "[1518-11-01 00:00] Guard #10 begins shift",
-            "[1518-11-01 00:05] falls asleep",
-            "[1518-11-01 00:25] wakes up",
-            "[1518-11-01 00:30] falls asleep",
-            "[1518-11-01 00:55] wakes up",
-            "[1518-11-01 23:58] Guard #99 begins shift",
-            "[1518-11-02 00:40] falls asleep",
-            "[1518-11-02 00:50] wakes up",
-            "[1518-11-03 00:05] Guard #10 begins shift",
-            "[1518-11-03 00:24] falls asleep",
-            "[1518-11-03 00:29] wakes up",
-            "[1518-11-04 00:02] Guard #99 begins shift",
-            "[1518-11-04 00:36] falls asleep",
-            "[1518-11-04 00:46] wakes up",
-            "[1518-11-05 00:03] Guard #99 begins shift",
-            "[1518-11-05 00:45] falls asleep",
-            "[1518-11-05 00:55] wakes up",
-        ]
-        .iter()
-        .map(|&s| String::from(s).parse::<Entry>().unwrap())
-        .collect();
+    fn part1(&self, input: Self::Input) -> Result<Answer, Error> {
+        Ok(solve1(input).into())
+    }
 
-        assert_eq!(solve2(records), 4455);
+    fn part2(&self, input: Self::Input) -> Result<Option<Answer>, Error> {
+        Ok(Some(solve2(input).into()))
     }
 }
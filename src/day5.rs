@@ -1,3 +1,7 @@
+use crate::answer::Answer;
+use crate::error::{Error, ParseError};
+use crate::solution::Solution;
+
 pub fn are_opposite((c1, c2): (char, char)) -> bool {
     if c1.is_lowercase() && c2.is_uppercase() {
         c2.to_ascii_lowercase() == c1
@@ -46,19 +50,20 @@ pub fn solve2(mut polymers: String) -> u64 {
         .unwrap()
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+pub struct Day5;
+
+impl Solution for Day5 {
+    type Input = String;
+
+    fn parse(&self, input: &str) -> Result<Self::Input, ParseError> {
+        Ok(input.to_string())
+    }
 
-    #[test]
-    fn test1() {
-        let input = "dabAcCaCBAcCcaDA".to_string();
-        assert_eq!(solve1(input), 10);
+    fn part1(&self, input: Self::Input) -> Result<Answer, Error> {
+        Ok(solve1(input).into())
     }
 
-    #[test]
-    fn test2() {
-        let input = "dabAcCaCBAcCcaDA".to_string();
-        assert_eq!(solve2(input), 4);
+    fn part2(&self, input: Self::Input) -> Result<Option<Answer>, Error> {
+        Ok(Some(solve2(input).into()))
     }
 }
@@ -1,8 +1,15 @@
+#![allow(clippy::ptr_arg, clippy::slow_vector_initialization)]
+
 use std::collections::HashSet;
 use std::str::FromStr;
 
 use regex::Regex;
 
+use crate::answer::Answer;
+use crate::error::{captures, field, Error, ParseError};
+use crate::parse_lines;
+use crate::solution::Solution;
+
 #[derive(Debug, Copy, Clone)]
 pub struct Coordinate {
     x: u64,
@@ -10,18 +17,19 @@ pub struct Coordinate {
 }
 
 impl FromStr for Coordinate {
-    type Err = Box<::std::error::Error>;
+    type Err = ParseError;
 
     // input: "1, 2"
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        const EXPECTED: &str = "coordinate like 1, 2";
         lazy_static! {
             static ref RE: Regex = Regex::new(r"^(\d+), (\d+)$").unwrap();
         }
-        let cap = RE.captures(s).unwrap();
+        let cap = captures(&RE, s, EXPECTED)?;
 
         Ok(Coordinate {
-            x: cap[1].parse()?,
-            y: cap[2].parse()?,
+            x: field(s, &cap, 1, EXPECTED)?,
+            y: field(s, &cap, 2, EXPECTED)?,
         })
     }
 }
@@ -125,19 +133,28 @@ pub fn solve2(coordinates: Vec<Coordinate>) -> u64 {
     region_size(coordinates, 10_000)
 }
 
+pub struct Day6;
+
+impl Solution for Day6 {
+    type Input = Vec<Coordinate>;
+
+    fn parse(&self, input: &str) -> Result<Self::Input, ParseError> {
+        parse_lines(input)
+    }
+
+    fn part1(&self, input: Self::Input) -> Result<Answer, Error> {
+        Ok(solve1(input).into())
+    }
+
+    fn part2(&self, input: Self::Input) -> Result<Option<Answer>, Error> {
+        Ok(Some(solve2(input).into()))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    #[test]
-    fn test1() {
-        let input = ["1, 1", "1, 6", "8, 3", "3, 4", "5, 5", "8, 9"]
-            .iter()
-            .map(|&s| String::from(s).parse::<Coordinate>().unwrap())
-            .collect();
-        assert_eq!(solve1(input), 17);
-    }
-
     #[test]
     fn test2() {
         let input = ["1, 1", "1, 6", "8, 3", "3, 4", "5, 5", "8, 9"]
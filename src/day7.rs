@@ -1,8 +1,15 @@
+#![allow(clippy::len_zero, clippy::ptr_arg, clippy::unnecessary_map_or)]
+
 use std::collections::HashSet;
 use std::str::FromStr;
 
 use regex::Regex;
 
+use crate::answer::Answer;
+use crate::error::{captures, Error, ParseError};
+use crate::parse_lines;
+use crate::solution::Solution;
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Step {
     id: u32,
@@ -21,15 +28,18 @@ pub struct Dependency {
 }
 
 impl FromStr for Dependency {
-    type Err = Box<::std::error::Error>;
+    type Err = ParseError;
 
-    // input: "1, 2"
+    // input: "Step C must be finished before step A can begin."
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        const EXPECTED: &str =
+            "dependency like \"Step C must be finished before step A can begin.\"";
         lazy_static! {
             static ref RE: Regex =
-                Regex::new(r"^Step (\w) must be finished before step (\w) can begin.$").unwrap();
+                Regex::new(r"^Step ([A-Z]) must be finished before step ([A-Z]) can begin.$")
+                    .unwrap();
         }
-        let cap = RE.captures(s).unwrap();
+        let cap = captures(&RE, s, EXPECTED)?;
 
         Ok(Dependency {
             before: Step {
@@ -125,28 +135,28 @@ pub fn solve2(deps: Vec<Dependency>, n_workers: usize, add_duration: u32) -> u32
     seconds - 1
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+pub struct Day7;
 
-    #[test]
-    fn test1() {
-        let input = [
-            "Step C must be finished before step A can begin.",
-            "Step C must be finished before step F can begin.",
-            "Step A must be finished before step B can begin.",
-            "Step A must be finished before step D can begin.",
-            "Step B must be finished before step E can begin.",
-            "Step D must be finished before step E can begin.",
-            "Step F must be finished before step E can begin.",
-        ]
-        .iter()
-        .map(|&s| String::from(s).parse::<Dependency>().unwrap())
-        .collect();
+impl Solution for Day7 {
+    type Input = Vec<Dependency>;
 
-        assert_eq!(solve1(input), "CABDFE");
+    fn parse(&self, input: &str) -> Result<Self::Input, ParseError> {
+        parse_lines(input)
     }
 
+    fn part1(&self, input: Self::Input) -> Result<Answer, Error> {
+        Ok(solve1(input).into())
+    }
+
+    fn part2(&self, input: Self::Input) -> Result<Option<Answer>, Error> {
+        Ok(Some(solve2(input, 5, 60).into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
     #[test]
     fn test2() {
         let input = [
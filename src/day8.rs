@@ -1,12 +1,18 @@
+#![allow(clippy::len_zero, clippy::redundant_field_names)]
+
 use std::str::FromStr;
 
-#[derive(Debug)]
+use crate::answer::Answer;
+use crate::error::{parse_as, Error, ParseError};
+use crate::solution::Solution;
+
+#[derive(Debug, Clone)]
 pub struct Node {
     childs: Vec<Node>,
     metadata: Vec<u64>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Tree {
     root: Node,
 }
@@ -39,16 +45,17 @@ impl Tree {
     }
 }
 
-// Return parsed nodes and where parsing ended in data.
-fn parse_node(data: &[u64], idx: usize, n_siblings: u64) -> (Vec<Node>, usize) {
-    let n_child = data[idx];
-    let n_metadata = data[idx + 1] as usize;
+// Return parsed nodes and where parsing ended in data,
+// or None if data ends before all nodes are complete.
+fn parse_node(data: &[u64], idx: usize, n_siblings: u64) -> Option<(Vec<Node>, usize)> {
+    let n_child = *data.get(idx)?;
+    let n_metadata = *data.get(idx + 1)? as usize;
 
     let (childs, metadata_start) = {
         if n_child == 0 {
             (vec![], idx + 2)
         } else {
-            parse_node(data, idx + 2, n_child)
+            parse_node(data, idx + 2, n_child)?
         }
     };
 
@@ -56,7 +63,7 @@ fn parse_node(data: &[u64], idx: usize, n_siblings: u64) -> (Vec<Node>, usize) {
         if n_siblings <= 1 {
             (vec![], metadata_start + n_metadata)
         } else {
-            parse_node(data, metadata_start + n_metadata, n_siblings - 1)
+            parse_node(data, metadata_start + n_metadata, n_siblings - 1)?
         }
     };
 
@@ -64,21 +71,35 @@ fn parse_node(data: &[u64], idx: usize, n_siblings: u64) -> (Vec<Node>, usize) {
         0,
         Node {
             childs: childs,
-            metadata: data[metadata_start..(metadata_start + n_metadata)].to_vec(),
+            metadata: data
+                .get(metadata_start..(metadata_start + n_metadata))?
+                .to_vec(),
         },
     );
 
-    (sibling_nodes, sibling_metadata_start)
+    Some((sibling_nodes, sibling_metadata_start))
 }
 
 impl FromStr for Tree {
-    type Err = Box<::std::error::Error>;
+    type Err = ParseError;
 
-    // input: "1, 2"
+    // input: "2 3 0 3 10 11 12 1 1 0 1 99 2 1 1 2"
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let data: Vec<u64> = s.split(' ').map(|s| s.trim().parse().unwrap()).collect();
-        let mut parsed = parse_node(&data, 0, 0).0;
-        assert!(parsed.len() == 1);
+        let mut data: Vec<u64> = vec![];
+        let mut columns = vec![];
+        let mut column = 1;
+        for d in s.split(' ') {
+            data.push(parse_as(d.trim(), "unsigned integer").map_err(|e| e.at_column(column))?);
+            columns.push(column);
+            column += d.len() + 1;
+        }
+        let (mut parsed, end) = parse_node(&data, 0, 0)
+            .ok_or_else(|| ParseError::new(s, "header and metadata for every node"))?;
+        if end < data.len() {
+            return Err(
+                ParseError::new(&s[columns[end] - 1..], "end of the tree").at_column(columns[end])
+            );
+        }
 
         Ok(Tree {
             root: parsed.pop().unwrap(),
@@ -87,40 +108,50 @@ impl FromStr for Tree {
 }
 
 // Solution: 47464
-pub fn solve1(mut input: Vec<Tree>) -> u64 {
-    assert!(input.len() == 1);
-    let tree = input.pop().unwrap();
+pub fn solve1(tree: Tree) -> u64 {
     tree.metadata_sum()
 }
 
 // Solution: 23054
-pub fn solve2(mut input: Vec<Tree>) -> u64 {
-    assert!(input.len() == 1);
-    let tree = input.pop().unwrap();
+pub fn solve2(tree: Tree) -> u64 {
     tree.root_value()
 }
 
+pub struct Day8;
+
+impl Solution for Day8 {
+    type Input = Tree;
+
+    // The whole tree is on one line.
+    fn parse(&self, input: &str) -> Result<Self::Input, ParseError> {
+        let lines: Vec<&str> = input.trim_end().lines().collect();
+        if let Some(extra) = lines.get(1) {
+            return Err(ParseError::new(extra, "tree on a single line").offset(1));
+        }
+        lines.first().copied().unwrap_or("").parse()
+    }
+
+    fn part1(&self, input: Self::Input) -> Result<Answer, Error> {
+        Ok(solve1(input).into())
+    }
+
+    fn part2(&self, input: Self::Input) -> Result<Option<Answer>, Error> {
+        Ok(Some(solve2(input).into()))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
-    fn test1() {
-        let input = ["2 3 0 3 10 11 12 1 1 0 1 99 2 1 1 2"]
-            .iter()
-            .map(|&s| String::from(s).parse::<Tree>().unwrap())
-            .collect();
-        println!("{:?}", input);
-        assert_eq!(solve1(input), 138);
-    }
-
-    #[test]
-    fn test2() {
-        let input = ["2 3 0 3 10 11 12 1 1 0 1 99 2 1 1 2"]
-            .iter()
-            .map(|&s| String::from(s).parse::<Tree>().unwrap())
-            .collect();
-        println!("{:?}", input);
-        assert_eq!(solve2(input), 66);
+    fn test_parse() {
+        let tree = Day8.parse("2 3 0 3 10 11 12 1 1 0 1 99 2 1 1 2\n").unwrap();
+        assert_eq!(solve1(tree), 138);
+
+        let err = Day8.parse("1 1 0 1 5 2 3").unwrap_err();
+        assert_eq!((err.text.as_str(), err.column), ("3", Some(13)));
+        let err = Day8.parse("0 1 5\n0 1 6").unwrap_err();
+        assert_eq!(err.line, Some(2));
     }
 }
@@ -1,4 +1,11 @@
+#![allow(clippy::redundant_pattern_matching, clippy::slow_vector_initialization)]
+
 use linked_list::LinkedList;
+use regex::Regex;
+
+use crate::answer::Answer;
+use crate::error::{captures, field, Error, ParseError};
+use crate::solution::Solution;
 
 // Solution 1: 367634
 // Solution 2: 3020072891
@@ -43,17 +50,63 @@ pub fn solve1(n_players: usize, last_marble: u64) -> u64 {
     *scores.iter().max().unwrap()
 }
 
+pub struct Day9;
+
+impl Solution for Day9 {
+    // (players, last marble)
+    type Input = (usize, u64);
+
+    // input: 479 players; last marble is worth 71035 points
+    fn parse(&self, input: &str) -> Result<Self::Input, ParseError> {
+        const EXPECTED: &str = "game like \"10 players; last marble is worth 1618 points\"";
+        lazy_static! {
+            static ref RE: Regex =
+                Regex::new(r"^(\d+) players; last marble is worth (\d+) points$").unwrap();
+        }
+        let s = input.trim();
+        let cap = captures(&RE, s, EXPECTED)?;
+        let n_players = field(s, &cap, 1, EXPECTED)?;
+        if n_players == 0 {
+            return Err(ParseError::new(s, "at least 1 player").at_column(1));
+        }
+
+        Ok((n_players, field(s, &cap, 2, EXPECTED)?))
+    }
+
+    fn part1(&self, (n_players, last_marble): Self::Input) -> Result<Answer, Error> {
+        Ok(solve1(n_players, last_marble).into())
+    }
+
+    fn part2(&self, (n_players, last_marble): Self::Input) -> Result<Option<Answer>, Error> {
+        let last_marble = last_marble.checked_mul(100).ok_or_else(|| {
+            Error::Solve(format!("last marble {} times 100 overflows", last_marble).into())
+        })?;
+        Ok(Some(solve1(n_players, last_marble).into()))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
-    fn test1() {
-        assert_eq!(solve1(9, 25), 32);
-        assert_eq!(solve1(10, 1618), 8317);
-        assert_eq!(solve1(13, 7999), 146373);
-        assert_eq!(solve1(17, 1104), 2764);
-        assert_eq!(solve1(21, 6111), 54718);
-        assert_eq!(solve1(30, 5807), 37305);
+    fn test_parse() {
+        assert_eq!(
+            Day9.parse("10 players; last marble is worth 1618 points\n"),
+            Ok((10, 1618))
+        );
+        assert!(Day9.parse("10 players").is_err());
+        assert!(Day9
+            .parse("0 players; last marble is worth 1618 points")
+            .is_err());
+    }
+
+    #[test]
+    fn test_part2_overflow() {
+        let e = Day9.part2((10, u64::MAX / 10)).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            format!("last marble {} times 100 overflows", u64::MAX / 10)
+        );
     }
 }
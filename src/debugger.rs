@@ -0,0 +1,601 @@
+// Interactive debugger for ElfCode programs, see `Debugger::parse` for the commands.
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::{self, Write};
+
+use crate::elfcode::{Snapshot, VM};
+use crate::error::{parse_as, ParseError};
+
+pub const HELP: &str = "commands:
+  s, step [n]                 execute n instructions, default 1
+  c, continue                 run until a breakpoint, watch or halt, at most 100 million steps
+  b, break <ip> [if <cond>]   stop before executing instruction ip
+  b, break if <cond>          stop before any instruction while cond holds
+  d, delete <n>               remove breakpoint n
+  w, watch <reg>              stop when the register changes
+  unwatch <reg>               remove watch
+  t, trace on|off             print every executed instruction
+  rs, reverse [n]             undo n instructions executed with trace on, default 1
+  save <path>                 write ip, registers, step count and i/o queues to a file
+  load <path>                 continue from a file written by save
+  r, registers                print registers
+  l, list                     print the program
+  h, help                     print this help
+  q, quit
+registers are named a, b, c, ... with the bound one named ip, or r0, r1, ...
+conditions compare a register with a value, e.g. c == 5, using == != < <= > >=";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cmp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Cmp {
+    fn test(self, l: i64, r: i64) -> bool {
+        match self {
+            Cmp::Eq => l == r,
+            Cmp::Ne => l != r,
+            Cmp::Lt => l < r,
+            Cmp::Le => l <= r,
+            Cmp::Gt => l > r,
+            Cmp::Ge => l >= r,
+        }
+    }
+}
+
+impl fmt::Display for Cmp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            Cmp::Eq => "==",
+            Cmp::Ne => "!=",
+            Cmp::Lt => "<",
+            Cmp::Le => "<=",
+            Cmp::Gt => ">",
+            Cmp::Ge => ">=",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+// Register value compared with a constant, e.g. "c == 5".
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Condition {
+    pub register: usize,
+    pub cmp: Cmp,
+    pub value: i64,
+}
+
+// Stop before executing instruction ip, or any instruction if ip is None,
+// when the condition holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Breakpoint {
+    pub ip: Option<i64>,
+    pub condition: Option<Condition>,
+}
+
+// Instructions that can be undone with reverse.
+const HISTORY: usize = 100_000;
+
+// Instructions continue executes before giving up, for programs that don't halt.
+const CONTINUE_STEPS: u64 = 100_000_000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    Step(u64),
+    Continue,
+    Break(Breakpoint),
+    Delete(usize),
+    Watch(usize),
+    Unwatch(usize),
+    Trace(bool),
+    Reverse(u64),
+    Save(String),
+    Load(String),
+    Registers,
+    List,
+    Help,
+    Quit,
+}
+
+pub struct Debugger {
+    pub vm: VM,
+    // Numbered from 1, deleted breakpoints keep their slot so numbers don't change.
+    breakpoints: Vec<Option<Breakpoint>>,
+    watches: Vec<usize>,
+    trace: bool,
+    // States before the latest instructions executed with trace on.
+    history: VecDeque<Snapshot>,
+    names: Vec<String>,
+    pub continue_steps: u64,
+}
+
+impl Debugger {
+    pub fn new(vm: VM) -> Debugger {
+        let names = vm.register_names();
+        Debugger {
+            vm,
+            breakpoints: vec![],
+            watches: vec![],
+            trace: false,
+            history: VecDeque::new(),
+            names,
+            continue_steps: CONTINUE_STEPS,
+        }
+    }
+
+    // Register by name, e.g. "c", "ip" or "r2".
+    fn parse_register(&self, s: &str) -> Result<usize, ParseError> {
+        let err = || ParseError::new(s, "register like c or r2");
+        let register = match self.names.iter().position(|n| n == s) {
+            Some(r) => r,
+            None => s
+                .strip_prefix('r')
+                .and_then(|r| r.parse().ok())
+                .ok_or_else(err)?,
+        };
+        if register >= self.vm.registers.len() {
+            return Err(err());
+        }
+        Ok(register)
+    }
+
+    // Condition like "c == 5".
+    fn parse_condition(&self, words: &[&str]) -> Result<Condition, ParseError> {
+        let s = words.join(" ");
+        let err = || ParseError::new(&s, "condition like c == 5");
+        if words.len() != 3 {
+            return Err(err());
+        }
+        let cmp = match words[1] {
+            "==" => Cmp::Eq,
+            "!=" => Cmp::Ne,
+            "<" => Cmp::Lt,
+            "<=" => Cmp::Le,
+            ">" => Cmp::Gt,
+            ">=" => Cmp::Ge,
+            _ => return Err(err()),
+        };
+
+        Ok(Condition {
+            register: self.parse_register(words[0])?,
+            cmp,
+            value: parse_as(words[2], "integer")?,
+        })
+    }
+
+    // Breakpoint like "28", "28 if c == 5" or "if c == 5".
+    fn parse_breakpoint(&self, words: &[&str]) -> Result<Breakpoint, ParseError> {
+        let s = words.join(" ");
+        let err = || ParseError::new(&s, "breakpoint like 28, 28 if c == 5 or if c == 5");
+        let (ip, rest) = match words {
+            ["if", ..] => (None, words),
+            [ip, rest @ ..] => (Some(parse_as(ip, "instruction index")?), rest),
+            [] => return Err(err()),
+        };
+        let condition = match rest {
+            [] => None,
+            ["if", condition @ ..] => Some(self.parse_condition(condition)?),
+            _ => return Err(err()),
+        };
+        if ip.is_none() && condition.is_none() {
+            return Err(err());
+        }
+
+        Ok(Breakpoint { ip, condition })
+    }
+
+    pub fn parse(&self, s: &str) -> Result<Command, ParseError> {
+        let words: Vec<&str> = s.split_whitespace().collect();
+        let (name, args) = match words.split_first() {
+            Some((name, args)) => (*name, args),
+            None => return Err(ParseError::new(s, "command, see help")),
+        };
+        let no_args = |command| {
+            if args.is_empty() {
+                Ok(command)
+            } else {
+                Err(ParseError::new(s, &format!("no arguments for {}", name)))
+            }
+        };
+        let one_arg = |expected: &str| match args {
+            [arg] => Ok(*arg),
+            _ => Err(ParseError::new(s, expected)),
+        };
+
+        match name {
+            "s" | "step" => match args {
+                [] => Ok(Command::Step(1)),
+                [n] => Ok(Command::Step(parse_as(n, "number of steps")?)),
+                _ => Err(ParseError::new(s, "step like step 10")),
+            },
+            "c" | "continue" => no_args(Command::Continue),
+            "b" | "break" => Ok(Command::Break(self.parse_breakpoint(args)?)),
+            "d" | "delete" => Ok(Command::Delete(parse_as(
+                one_arg("delete like delete 1")?,
+                "breakpoint number",
+            )?)),
+            "w" | "watch" => Ok(Command::Watch(
+                self.parse_register(one_arg("watch like watch c")?)?,
+            )),
+            "unwatch" => Ok(Command::Unwatch(
+                self.parse_register(one_arg("unwatch like unwatch c")?)?,
+            )),
+            "t" | "trace" => match one_arg("trace on or trace off")? {
+                "on" => Ok(Command::Trace(true)),
+                "off" => Ok(Command::Trace(false)),
+                _ => Err(ParseError::new(s, "trace on or trace off")),
+            },
+            "rs" | "reverse" => match args {
+                [] => Ok(Command::Reverse(1)),
+                [n] => Ok(Command::Reverse(parse_as(n, "number of steps")?)),
+                _ => Err(ParseError::new(s, "reverse like reverse 10")),
+            },
+            "save" => Ok(Command::Save(
+                one_arg("save like save state.txt")?.to_string(),
+            )),
+            "load" => Ok(Command::Load(
+                one_arg("load like load state.txt")?.to_string(),
+            )),
+            "r" | "registers" => no_args(Command::Registers),
+            "l" | "list" => no_args(Command::List),
+            "h" | "help" => no_args(Command::Help),
+            "q" | "quit" => no_args(Command::Quit),
+            _ => Err(ParseError::new(s, "command, see help")),
+        }
+    }
+
+    fn format_condition(&self, c: &Condition) -> String {
+        format!("{} {} {}", self.names[c.register], c.cmp, c.value)
+    }
+
+    fn format_breakpoint(&self, b: &Breakpoint) -> String {
+        match (b.ip, &b.condition) {
+            (Some(ip), None) => format!("at {}", ip),
+            (Some(ip), Some(c)) => format!("at {} if {}", ip, self.format_condition(c)),
+            (None, Some(c)) => format!("if {}", self.format_condition(c)),
+            (None, None) => "always".to_string(),
+        }
+    }
+
+    // Instruction at ip with its decompiled form.
+    fn format_instruction(&self, ip: i64) -> String {
+        match self.vm.program.get(ip as usize).filter(|_| ip >= 0) {
+            Some(ins) => format!(
+                "{:>4}: {:<20}{}",
+                ip,
+                ins.to_string(),
+                self.vm.decompile(ins)
+            ),
+            None => format!("{:>4}: halted", ip),
+        }
+    }
+
+    fn format_registers(&self) -> String {
+        self.names
+            .iter()
+            .zip(self.vm.registers.iter())
+            .map(|(n, v)| format!("{}={}", n, v))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    // First breakpoint stopping before the next instruction.
+    fn breakpoint_hit(&self) -> Option<usize> {
+        let ip = self.vm.ip;
+        let registers = &self.vm.registers;
+        self.breakpoints.iter().position(|b| match b {
+            Some(b) => {
+                b.ip.is_none_or(|b| b == ip)
+                    && b.condition
+                        .is_none_or(|c| c.cmp.test(registers[c.register], c.value))
+            }
+            None => false,
+        })
+    }
+
+    // Execute up to limit instructions, stopping early at breakpoints and watches
+    // except before the first one. Reports running out of instructions if expected
+    // to stop before.
+    fn run(&mut self, limit: u64, report_limit: bool, out: &mut impl Write) -> io::Result<()> {
+        let mut n = 0;
+        let mut stopped = false;
+        while n < limit {
+            if n > 0 {
+                if let Some(i) = self.breakpoint_hit() {
+                    let b = self.breakpoints[i].unwrap();
+                    writeln!(out, "breakpoint {} {}", i + 1, self.format_breakpoint(&b))?;
+                    stopped = true;
+                    break;
+                }
+            }
+
+            let ip = self.vm.ip;
+            let before = self.vm.snapshot();
+            match self.vm.step() {
+                Ok(true) => {}
+                Ok(false) => {
+                    writeln!(out, "halted after {} steps", self.vm.steps)?;
+                    return Ok(());
+                }
+                Err(e) => {
+                    writeln!(out, "error: {}", e)?;
+                    return Ok(());
+                }
+            }
+            n += 1;
+            if self.trace {
+                if self.history.len() == HISTORY {
+                    self.history.pop_front();
+                }
+                self.history.push_back(before.clone());
+            } else {
+                self.history.clear();
+            }
+            if self.trace {
+                writeln!(out, "{}", self.format_instruction(ip))?;
+            }
+            for o in self.vm.output.drain(..) {
+                writeln!(out, "output {} from {}", o.value, o.ip)?;
+            }
+
+            let mut changed = false;
+            for &r in &self.watches {
+                if before.registers[r] != self.vm.registers[r] {
+                    writeln!(
+                        out,
+                        "watch {}: {} -> {}",
+                        self.names[r], before.registers[r], self.vm.registers[r]
+                    )?;
+                    changed = true;
+                }
+            }
+            if changed {
+                stopped = true;
+                break;
+            }
+        }
+        if report_limit && !stopped {
+            writeln!(out, "no stop after {} steps, continue to run more", n)?;
+        }
+
+        writeln!(out, "{}", self.format_instruction(self.vm.ip))
+    }
+
+    pub fn execute(&mut self, command: Command, out: &mut impl Write) -> io::Result<()> {
+        match command {
+            Command::Step(n) => self.run(n, false, out)?,
+            Command::Continue => self.run(self.continue_steps, true, out)?,
+            Command::Break(b) => {
+                self.breakpoints.push(Some(b));
+                writeln!(
+                    out,
+                    "breakpoint {} {}",
+                    self.breakpoints.len(),
+                    self.format_breakpoint(&b)
+                )?;
+            }
+            Command::Delete(i) => match self.breakpoints.get_mut(i.wrapping_sub(1)) {
+                Some(b @ Some(_)) => *b = None,
+                _ => writeln!(out, "no breakpoint {}", i)?,
+            },
+            Command::Watch(r) => {
+                if !self.watches.contains(&r) {
+                    self.watches.push(r);
+                }
+            }
+            Command::Unwatch(r) => self.watches.retain(|&w| w != r),
+            Command::Trace(on) => self.trace = on,
+            Command::Reverse(n) => {
+                if self.history.is_empty() {
+                    writeln!(out, "no history, turn trace on to record it")?;
+                    return Ok(());
+                }
+                let keep = self.history.len().saturating_sub(n as usize);
+                let snapshot = self.history.drain(keep..).next().unwrap();
+                // History only holds snapshots of this VM.
+                self.vm.restore(&snapshot).unwrap();
+                writeln!(out, "{}", self.format_instruction(self.vm.ip))?;
+            }
+            Command::Save(path) => match self.vm.snapshot().save(&path) {
+                Ok(()) => writeln!(out, "saved step {} to {}", self.vm.steps, path)?,
+                Err(e) => writeln!(out, "error: {}", e)?,
+            },
+            Command::Load(path) => match Snapshot::load(&path) {
+                Ok(s) => match self.vm.restore(&s) {
+                    Ok(()) => {
+                        self.history.clear();
+                        writeln!(out, "{}", self.format_instruction(self.vm.ip))?;
+                    }
+                    Err(e) => writeln!(out, "error: {}: {}", path, e)?,
+                },
+                Err(e) => writeln!(out, "error: {}", e)?,
+            },
+            Command::Registers => {
+                writeln!(out, "{}", self.format_registers())?;
+                writeln!(out, "ip={} steps={}", self.vm.ip, self.vm.steps)?;
+            }
+            Command::List => {
+                for ip in 0..self.vm.program.len() as i64 {
+                    let current = if ip == self.vm.ip { "=>" } else { "  " };
+                    let breakpoint = self
+                        .breakpoints
+                        .iter()
+                        .any(|b| b.is_some_and(|b| b.ip == Some(ip)));
+                    let breakpoint = if breakpoint { "*" } else { " " };
+                    writeln!(
+                        out,
+                        "{}{}{}",
+                        current,
+                        breakpoint,
+                        self.format_instruction(ip)
+                    )?;
+                }
+            }
+            Command::Help => writeln!(out, "{}", HELP)?,
+            Command::Quit => {}
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extensions::extended;
+
+    const PROGRAM: &str = "#ip 0
+seti 5 0 1
+seti 6 0 2
+addi 0 1 0
+addr 1 2 3
+setr 1 0 0
+seti 8 0 4
+seti 9 0 5";
+
+    fn execute(debugger: &mut Debugger, command: &str) -> String {
+        let command = debugger.parse(command).unwrap();
+        let mut out = vec![];
+        debugger.execute(command, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_breakpoints() {
+        let mut debugger = Debugger::new(PROGRAM.parse().unwrap());
+        assert_eq!(execute(&mut debugger, "break 4"), "breakpoint 1 at 4\n");
+        assert_eq!(
+            execute(&mut debugger, "continue"),
+            "breakpoint 1 at 4\n   4: setr 1 0 0          ip = b\n"
+        );
+        assert_eq!(
+            execute(&mut debugger, "r"),
+            "ip=3 b=5 c=6 d=0 e=0 f=0\nip=4 steps=3\n"
+        );
+        assert_eq!(execute(&mut debugger, "c"), "halted after 5 steps\n");
+
+        let mut debugger = Debugger::new(PROGRAM.parse().unwrap());
+        execute(&mut debugger, "b if c >= 6");
+        assert!(execute(&mut debugger, "c").starts_with("breakpoint 1 if c >= 6\n   2:"));
+        execute(&mut debugger, "delete 1");
+        assert_eq!(execute(&mut debugger, "c"), "halted after 5 steps\n");
+    }
+
+    #[test]
+    fn test_watch_and_trace() {
+        let mut debugger = Debugger::new(PROGRAM.parse().unwrap());
+        execute(&mut debugger, "watch r5");
+        execute(&mut debugger, "trace on");
+        let out = execute(&mut debugger, "c");
+        assert_eq!(out.lines().count(), 7);
+        assert!(out.contains("watch f: 0 -> 9\n"));
+        assert_eq!(execute(&mut debugger, "step 2"), "halted after 5 steps\n");
+    }
+
+    #[test]
+    fn test_reverse() {
+        let mut debugger = Debugger::new(PROGRAM.parse().unwrap());
+        assert_eq!(
+            execute(&mut debugger, "reverse"),
+            "no history, turn trace on to record it\n"
+        );
+        execute(&mut debugger, "trace on");
+        execute(&mut debugger, "step 3");
+        assert_eq!(
+            execute(&mut debugger, "rs 2"),
+            "   1: seti 6 0 2          c = 6\n"
+        );
+        assert_eq!(
+            execute(&mut debugger, "r"),
+            "ip=0 b=5 c=0 d=0 e=0 f=0\nip=1 steps=1\n"
+        );
+        assert_eq!(
+            execute(&mut debugger, "rs 5"),
+            "   0: seti 5 0 1          b = 5\n"
+        );
+        assert!(execute(&mut debugger, "c").ends_with("halted after 5 steps\n"));
+
+        // Stepping back returns consumed input, so stepping forward reads it again.
+        let vm = VM::parse("in 0 0 0\nout 0 0 0\nin 0 0 0", &extended()).unwrap();
+        let mut debugger = Debugger::new(vm);
+        debugger.vm.input.extend([4, 7]);
+        execute(&mut debugger, "trace on");
+        assert!(execute(&mut debugger, "step 2").contains("output 4 from 1\n"));
+        execute(&mut debugger, "rs 2");
+        assert_eq!(debugger.vm.input, [4, 7]);
+        assert!(execute(&mut debugger, "step 2").contains("output 4 from 1\n"));
+        assert!(execute(&mut debugger, "c").ends_with("halted after 3 steps\n"));
+    }
+
+    #[test]
+    fn test_continue_limit() {
+        let mut debugger = Debugger::new("#ip 0\nseti -1 0 0".parse().unwrap());
+        debugger.continue_steps = 10;
+        assert_eq!(
+            execute(&mut debugger, "c"),
+            "no stop after 10 steps, continue to run more\n   0: seti -1 0 0         ip = -1\n"
+        );
+        assert_eq!(debugger.vm.steps, 10);
+    }
+
+    #[test]
+    fn test_save_load() {
+        let path = std::env::temp_dir().join(format!("elfcode-{}.txt", std::process::id()));
+        let path = path.to_str().unwrap();
+        let mut debugger = Debugger::new(PROGRAM.parse().unwrap());
+        execute(&mut debugger, "step 2");
+        assert_eq!(
+            execute(&mut debugger, &format!("save {}", path)),
+            format!("saved step 2 to {}\n", path)
+        );
+        execute(&mut debugger, "c");
+
+        let mut other = Debugger::new("#ip 0\nseti 5 0 1".parse().unwrap());
+        assert_eq!(
+            execute(&mut other, &format!("load {}", path)),
+            format!(
+                "error: {}: snapshot of 7 instructions and 6 registers, expected 1 and 6\n",
+                path
+            )
+        );
+
+        let mut debugger = Debugger::new(PROGRAM.parse().unwrap());
+        assert_eq!(
+            execute(&mut debugger, &format!("load {}", path)),
+            "   2: addi 0 1 0          ip = ip + 1\n"
+        );
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(
+            execute(&mut debugger, "r"),
+            "ip=1 b=5 c=6 d=0 e=0 f=0\nip=2 steps=2\n"
+        );
+        assert!(execute(&mut debugger, &format!("load {}", path)).starts_with("error: "));
+    }
+
+    #[test]
+    fn test_parse() {
+        let debugger = Debugger::new(PROGRAM.parse().unwrap());
+        assert_eq!(debugger.parse("s 3"), Ok(Command::Step(3)));
+        assert_eq!(
+            debugger.parse("break 28 if ip != -1"),
+            Ok(Command::Break(Breakpoint {
+                ip: Some(28),
+                condition: Some(Condition {
+                    register: 0,
+                    cmp: Cmp::Ne,
+                    value: -1
+                })
+            }))
+        );
+        assert!(debugger.parse("break").is_err());
+        assert!(debugger.parse("break if g == 1").is_err());
+        assert!(debugger.parse("watch r6").is_err());
+        assert!(debugger.parse("continue 2").is_err());
+        assert!(debugger.parse("jump 3").is_err());
+    }
+}
@@ -0,0 +1,630 @@
+// Decompile an ElfCode program into structured pseudo-code.
+//
+// Jumps are writes to the bound ip register. Writes computed from ip and immediates
+// jump to a known target, "ip = x + ip" right after a comparison into x is a
+// conditional branch over the next instruction, anything else is an indirect jump.
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+use crate::elfcode::{Instruction, Operand, VM};
+
+// Where control goes after a basic block, targets are instruction indices
+// and anything outside the program halts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Exit {
+    Jump(i64),
+    // Jump to then if the comparison at instruction `compare` is true.
+    Branch {
+        compare: usize,
+        then: i64,
+        otherwise: i64,
+    },
+    // Jump computed by instruction `jump` from registers other than ip.
+    Indirect(usize),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Block {
+    pub start: usize,
+    // One past the last instruction, including the jump.
+    pub end: usize,
+    pub exit: Exit,
+}
+
+#[derive(Debug, Clone)]
+pub struct Cfg {
+    pub blocks: Vec<Block>,
+}
+
+// Jump done by instruction i, or None if it doesn't write ip.
+fn control(vm: &VM, i: usize, leaders: &BTreeSet<usize>) -> Option<Exit> {
+    let ins = &vm.program[i];
+    let ip = vm.ip_register.filter(|&ip| ins.c as usize == ip)?;
+
+    if let Some(v) = ins.eval(|r| if r == ip { Some(i as i64) } else { None }) {
+        return Some(Exit::Jump(v + 1));
+    }
+    if ins.opcode.is_comparison() {
+        return Some(Exit::Branch {
+            compare: i,
+            then: 2,
+            otherwise: 1,
+        });
+    }
+    // "ip = x + ip" skips the next instruction if the comparison before set x.
+    if let [Operand::Register(a), Operand::Register(b)] = ins.inputs() {
+        if ins.opcode.operator() == Some("+") && (a == ip) != (b == ip) {
+            let x = if a == ip { b } else { a };
+            let prev = i.checked_sub(1).map(|p| &vm.program[p]);
+            if let Some(prev) = prev.filter(|p| p.opcode.is_comparison() && p.c as usize == x) {
+                if !leaders.contains(&i) && prev.c as usize != ip {
+                    return Some(Exit::Branch {
+                        compare: i - 1,
+                        then: i as i64 + 2,
+                        otherwise: i as i64 + 1,
+                    });
+                }
+            }
+        }
+    }
+
+    Some(Exit::Indirect(i))
+}
+
+impl Exit {
+    pub fn targets(&self) -> Vec<i64> {
+        match *self {
+            Exit::Jump(t) => vec![t],
+            Exit::Branch {
+                then, otherwise, ..
+            } => vec![then, otherwise],
+            Exit::Indirect(_) => vec![],
+        }
+    }
+}
+
+impl Cfg {
+    pub fn new(vm: &VM) -> Cfg {
+        let n = vm.program.len();
+        let mut exits = vec![None; n];
+        let mut leaders: BTreeSet<usize> = BTreeSet::new();
+        leaders.insert(0);
+
+        // A new leader can turn a branch into an indirect jump, repeat until stable.
+        loop {
+            let mut changed = false;
+            for (i, exit) in exits.iter_mut().enumerate() {
+                let e = control(vm, i, &leaders);
+                if e != *exit {
+                    *exit = e;
+                    changed = true;
+                }
+            }
+            for (i, exit) in exits.iter().enumerate() {
+                if let Some(exit) = exit {
+                    leaders.insert(i + 1);
+                    leaders.extend(
+                        exit.targets()
+                            .into_iter()
+                            .filter(|&t| t >= 0)
+                            .map(|t| t as usize),
+                    );
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+
+        let starts: Vec<usize> = leaders.into_iter().filter(|&l| l < n).collect();
+        let blocks = starts
+            .iter()
+            .enumerate()
+            .map(|(i, &start)| {
+                let end = starts.get(i + 1).cloned().unwrap_or(n);
+                Block {
+                    start,
+                    end,
+                    exit: exits[end - 1].unwrap_or(Exit::Jump(end as i64)),
+                }
+            })
+            .collect();
+
+        Cfg { blocks }
+    }
+
+    // Block starting at instruction ip, or the number of blocks if it halts.
+    pub fn block_at(&self, ip: i64) -> usize {
+        self.blocks
+            .iter()
+            .position(|b| b.start as i64 == ip)
+            .unwrap_or(self.blocks.len())
+    }
+
+    pub fn successors(&self, b: usize) -> Vec<usize> {
+        self.blocks[b]
+            .exit
+            .targets()
+            .into_iter()
+            .map(|t| self.block_at(t))
+            .collect()
+    }
+}
+
+// Registers live after block b given those live at the start of each block.
+// Programs leave their result in register 0, so only it is live when halting.
+fn live_out(cfg: &Cfg, live: &[BTreeSet<usize>], b: usize) -> BTreeSet<usize> {
+    let mut out = BTreeSet::new();
+    for s in cfg.successors(b) {
+        match live.get(s) {
+            Some(l) => out.extend(l),
+            None => {
+                out.insert(0);
+            }
+        }
+    }
+    out
+}
+
+// Registers live at the start of each block.
+fn live_in(vm: &VM, cfg: &Cfg) -> Vec<BTreeSet<usize>> {
+    let ip = vm.ip_register;
+    let mut live = vec![BTreeSet::new(); cfg.blocks.len()];
+
+    loop {
+        let mut changed = false;
+        for b in (0..cfg.blocks.len()).rev() {
+            let block = &cfg.blocks[b];
+            let mut l = match block.exit {
+                Exit::Indirect(_) => (0..vm.registers.len()).collect(),
+                _ => live_out(cfg, &live, b),
+            };
+            for ins in vm.program[block.start..block.end].iter().rev() {
+                l.remove(&(ins.c as usize));
+                l.extend(ins.reads().filter(|&r| Some(r) != ip));
+            }
+            if l != live[b] {
+                live[b] = l;
+                changed = true;
+            }
+        }
+        if !changed {
+            break live;
+        }
+    }
+}
+
+struct Decompiler<'a> {
+    vm: &'a VM,
+    cfg: &'a Cfg,
+    names: Vec<String>,
+    // Reachable blocks in program order, the decompiler numbers blocks by
+    // position in this list and uses its length for halting.
+    order: Vec<usize>,
+    // Successors skipping blocks that only jump.
+    succ: Vec<Vec<usize>>,
+    // Comparisons only used by the branch after them, printed as its condition.
+    folded: BTreeSet<usize>,
+    labels: BTreeSet<usize>,
+    // Header and exit block of enclosing loops.
+    loops: Vec<(usize, usize)>,
+    out: String,
+}
+
+impl<'a> Decompiler<'a> {
+    fn new(vm: &'a VM, cfg: &'a Cfg) -> Decompiler<'a> {
+        let n = cfg.blocks.len();
+        let live = live_in(vm, cfg);
+
+        let mut folded = BTreeSet::new();
+        for (b, block) in cfg.blocks.iter().enumerate() {
+            if let Exit::Branch { compare, .. } = block.exit {
+                let c = vm.program[compare].c as usize;
+                if compare + 2 == block.end && !live_out(cfg, &live, b).contains(&c) {
+                    folded.insert(compare);
+                }
+            }
+        }
+
+        // Follow blocks without statements ending in a jump, up to n times in case they loop.
+        let only_jumps = |b: usize| {
+            let block = &cfg.blocks[b];
+            block.end == block.start + 1
+                && matches!(block.exit, Exit::Jump(t) if t != block.end as i64)
+        };
+        let thread = |mut b: usize| {
+            for _ in 0..n {
+                if b == n || !only_jumps(b) {
+                    break;
+                }
+                b = cfg.successors(b)[0];
+            }
+            b
+        };
+        let threaded: Vec<Vec<usize>> = (0..n)
+            .map(|b| cfg.successors(b).into_iter().map(thread).collect())
+            .collect();
+
+        // Indirect jumps can go to any block after them, they add a register to ip
+        // and registers are assumed to be non-negative.
+        let mut reachable = vec![false; n];
+        let mut todo = vec![0];
+        let mut indirect = n;
+        while let Some(b) = todo.pop() {
+            if b < n && !reachable[b] {
+                reachable[b] = true;
+                todo.extend(&threaded[b]);
+                if let Exit::Indirect(_) = cfg.blocks[b].exit {
+                    indirect = indirect.min(b);
+                    todo.extend(b + 1..n);
+                }
+            }
+        }
+        let order: Vec<usize> = (0..n).filter(|&b| reachable[b]).collect();
+        let position = |b: usize| order.iter().position(|&o| o == b).unwrap_or(order.len());
+        let succ = order
+            .iter()
+            .map(|&b| threaded[b].iter().map(|&s| position(s)).collect())
+            .collect();
+
+        let labels = (0..order.len()).filter(|&b| order[b] > indirect).collect();
+
+        Decompiler {
+            vm,
+            cfg,
+            names: vm.register_names(),
+            order,
+            succ,
+            folded,
+            labels,
+            loops: vec![],
+            out: String::new(),
+        }
+    }
+
+    fn block(&self, b: usize) -> &Block {
+        &self.cfg.blocks[self.order[b]]
+    }
+
+    fn line(&mut self, depth: usize, s: &str) {
+        writeln!(self.out, "{:width$}{}", "", s, width = depth * 4).unwrap();
+    }
+
+    // Operand as register name or value, reading ip gives the instruction index.
+    fn operand(&self, op: Operand, i: usize) -> String {
+        match op {
+            Operand::Register(r) if Some(r) == self.vm.ip_register => i.to_string(),
+            Operand::Register(r) => self.names[r].clone(),
+            Operand::Immediate(v) => v.to_string(),
+            Operand::Unused => String::new(),
+        }
+    }
+
+    fn expression(&self, ins: &Instruction, i: usize, negate: bool) -> String {
+        let [a, b] = ins.inputs();
+        let (a, b) = (self.operand(a, i), self.operand(b, i));
+        match (ins.opcode.operator(), negate) {
+            (None, _) => ins.opcode.expression(&a, &b),
+            (Some(">"), true) => format!("{} <= {}", a, b),
+            (Some("=="), true) => format!("{} != {}", a, b),
+            (Some(op), _) => format!("{} {} {}", a, op, b),
+        }
+    }
+
+    fn statement(&self, i: usize) -> String {
+        let ins = &self.vm.program[i];
+        let c = &self.names[ins.c as usize];
+        if ins.opcode.is_comparison() {
+            format!(
+                "{} = if {} {{ 1 }} else {{ 0 }}",
+                c,
+                self.expression(ins, i, false)
+            )
+        } else {
+            format!("{} = {}", c, self.expression(ins, i, false))
+        }
+    }
+
+    // Condition of a branch block, true if it jumps to `then`.
+    fn condition(&self, b: usize, negate: bool) -> String {
+        let block = self.block(b);
+        match block.exit {
+            Exit::Branch { compare, .. }
+                if compare + 1 == block.end || self.folded.contains(&compare) =>
+            {
+                self.expression(&self.vm.program[compare], compare, negate)
+            }
+            Exit::Branch { compare, .. } => {
+                let x = &self.names[self.vm.program[compare].c as usize];
+                format!("{} {} 0", x, if negate { "==" } else { "!=" })
+            }
+            _ => unreachable!("condition of block without branch"),
+        }
+    }
+
+    fn statements(&self, b: usize) -> Vec<usize> {
+        let block = self.block(b);
+        let jump = match block.exit {
+            Exit::Jump(t) if t == block.end as i64 => None,
+            _ => Some(block.end - 1),
+        };
+        (block.start..block.end)
+            .filter(|&i| Some(i) != jump && !self.folded.contains(&i))
+            .collect()
+    }
+
+    // Statement jumping to block t, None if control gets there anyway.
+    fn jump(&mut self, t: usize, natural: Option<usize>) -> Option<String> {
+        if Some(t) == natural {
+            return None;
+        }
+        if t == self.order.len() {
+            return Some("halt".to_string());
+        }
+        if let Some(&(header, exit)) = self.loops.last() {
+            if t == header {
+                return Some("continue".to_string());
+            }
+            if t == exit {
+                return Some("break".to_string());
+            }
+        }
+        self.labels.insert(t);
+        Some(format!("goto L{}", self.block(t).start))
+    }
+
+    fn label(&mut self, b: usize) {
+        if self.labels.contains(&b) {
+            let label = format!("L{}:\n", self.block(b).start);
+            self.out.push_str(&label);
+        }
+    }
+
+    // Emit blocks lo..hi, after which control continues at block follow.
+    fn region(&mut self, lo: usize, hi: usize, follow: usize, depth: usize) {
+        let mut b = lo;
+        while b < hi {
+            let in_header = b == lo && self.loops.last().map(|l| l.0) == Some(b);
+            if !in_header {
+                self.label(b);
+                if let Some(latch) = (b..hi).rev().find(|&l| self.succ[l].contains(&b)) {
+                    self.emit_loop(b, latch + 1, depth);
+                    b = latch + 1;
+                    continue;
+                }
+            }
+
+            for i in self.statements(b) {
+                let s = self.statement(i);
+                self.line(depth, &s);
+            }
+            b = self.exit(b, hi, follow, depth);
+        }
+    }
+
+    // Emit region into a separate buffer.
+    fn nested(&mut self, lo: usize, hi: usize, follow: usize, depth: usize) -> String {
+        let out = std::mem::take(&mut self.out);
+        self.region(lo, hi, follow, depth);
+        std::mem::replace(&mut self.out, out)
+    }
+
+    fn emit_loop(&mut self, header: usize, exit: usize, depth: usize) {
+        self.loops.push((header, exit));
+        let succ = self.succ[header].clone();
+        let is_branch = matches!(self.block(header).exit, Exit::Branch { .. });
+        let body = succ.iter().position(|&s| s == header + 1 && s != exit);
+        match body {
+            Some(body)
+                if is_branch && self.statements(header).is_empty() && succ.contains(&exit) =>
+            {
+                // succ[0] is then, loop while the branch goes to the body.
+                let cond = self.condition(header, body == 1);
+                self.line(depth, &format!("while {} {{", cond));
+                self.region(header + 1, exit, header, depth + 1);
+            }
+            _ => {
+                self.line(depth, "loop {");
+                self.region(header, exit, header, depth + 1);
+            }
+        }
+        self.line(depth, "}");
+        self.loops.pop();
+    }
+
+    // Emit the exit of block b and return the next block to emit.
+    fn exit(&mut self, b: usize, hi: usize, follow: usize, depth: usize) -> usize {
+        let next = b + 1;
+        let natural = if next == hi { follow } else { next };
+        match self.block(b).exit {
+            Exit::Jump(_) => {
+                if let Some(s) = self.jump(self.succ[b][0], Some(natural)) {
+                    self.line(depth, &s);
+                }
+                next
+            }
+            Exit::Branch { .. } => {
+                let (then, otherwise) = (self.succ[b][0], self.succ[b][1]);
+                if then != natural && otherwise != natural {
+                    let cond = self.condition(b, false);
+                    let s = self.jump(then, None).unwrap();
+                    self.line(depth, &format!("if {} {{ {} }}", cond, s));
+                    if let Some(s) = self.jump(otherwise, Some(natural)) {
+                        self.line(depth, &s);
+                    }
+                    return next;
+                }
+
+                // Branch to other, otherwise continue with the natural block.
+                let (other, negate) = if otherwise == natural {
+                    (then, false)
+                } else {
+                    (otherwise, true)
+                };
+                let in_region = |t: usize| t > next && (t < hi || t == follow);
+                if natural != next || !in_region(other) {
+                    let cond = self.condition(b, negate);
+                    let s = self.jump(other, None).unwrap();
+                    self.line(depth, &format!("if {} {{ {} }}", cond, s));
+                    return next;
+                }
+
+                // Structured if, with an else when the last block before other
+                // jumps over the blocks from other.
+                let last = other - 1;
+                let join = match self.block(last).exit {
+                    Exit::Jump(_) if last > b => Some(self.succ[last][0]),
+                    _ => None,
+                };
+                let (then_end, join) = match join.filter(|&j| j > other && in_region(j)) {
+                    Some(join) => (other, join),
+                    None => (other, other),
+                };
+                let then_s = self.nested(next, then_end, join, depth + 1);
+                let else_s = self.nested(other, join, join, depth + 1);
+                if then_s.is_empty() {
+                    let cond = self.condition(b, negate);
+                    self.line(depth, &format!("if {} {{", cond));
+                    self.out.push_str(&else_s);
+                } else {
+                    let cond = self.condition(b, !negate);
+                    self.line(depth, &format!("if {} {{", cond));
+                    self.out.push_str(&then_s);
+                    if !else_s.is_empty() {
+                        self.line(depth, "} else {");
+                        self.out.push_str(&else_s);
+                    }
+                }
+                self.line(depth, "}");
+                join
+            }
+            Exit::Indirect(i) => {
+                let ins = &self.vm.program[i];
+                let target = self.expression(ins, i, false);
+                self.line(depth, &format!("goto *({} + 1)", target));
+                next
+            }
+        }
+    }
+}
+
+// Structured pseudo-code for the whole program, with ip reads replaced by the
+// instruction index and labels named after the instruction they start at.
+pub fn decompile(vm: &VM) -> String {
+    let cfg = Cfg::new(vm);
+    let mut decompiler = Decompiler::new(vm, &cfg);
+    let n = decompiler.order.len();
+
+    // First pass finds the blocks needing labels.
+    decompiler.region(0, n, n, 0);
+    decompiler.out.clear();
+    decompiler.region(0, n, n, 0);
+
+    decompiler.out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cfg() {
+        let vm: VM = "#ip 5
+seti 0 0 1
+addi 1 1 1
+gtri 1 9 2
+addr 2 5 5
+seti 0 0 5
+seti 7 0 0"
+            .parse()
+            .unwrap();
+        let cfg = Cfg::new(&vm);
+        let exits: Vec<_> = cfg.blocks.iter().map(|b| (b.start, b.exit)).collect();
+        assert_eq!(
+            exits,
+            vec![
+                (0, Exit::Jump(1)),
+                (
+                    1,
+                    Exit::Branch {
+                        compare: 2,
+                        then: 5,
+                        otherwise: 4
+                    }
+                ),
+                (4, Exit::Jump(1)),
+                (5, Exit::Jump(6)),
+            ]
+        );
+
+        assert_eq!(
+            decompile(&vm),
+            "b = 0
+loop {
+    b = b + 1
+    if b > 9 { break }
+}
+a = 7
+"
+        );
+    }
+
+    #[test]
+    fn test_while() {
+        let vm: VM = "#ip 5
+seti 0 0 1
+gtri 1 9 2
+addr 2 5 5
+addi 5 1 5
+seti 6 0 5
+addi 1 1 1
+seti 0 0 5
+seti 7 0 0"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            decompile(&vm),
+            "b = 0
+while b <= 9 {
+    b = b + 1
+}
+a = 7
+"
+        );
+    }
+
+    // Liveness isn't limited to 64 registers.
+    #[test]
+    fn test_many_registers() {
+        let program = ["gtri 66 5 1", "addr 1 69 69", "seti 7 0 66", "seti 7 0 0"]
+            .iter()
+            .map(|ins| ins.parse().unwrap())
+            .collect();
+        let vm = VM::new(70, Some(69), program).unwrap();
+        assert!(decompile(&vm).ends_with("a = 7\n"));
+    }
+
+    #[test]
+    fn test_if_else() {
+        let vm: VM = "#ip 5
+gtri 0 5 1
+addr 1 5 5
+seti 4 0 5
+seti 2 0 2
+seti 5 0 5
+seti 1 0 2
+addr 2 0 0"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            decompile(&vm),
+            "if a > 5 {
+    c = 2
+} else {
+    c = 1
+}
+a = c + a
+"
+        );
+    }
+}
@@ -0,0 +1,989 @@
+// ElfCode, the instruction set shared by days 16, 19 and 21.
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+use std::error;
+use std::fmt;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::ops::ControlFlow;
+use std::str::FromStr;
+
+use crate::error::{parse_as, Error, ParseError};
+use crate::optimizer::{self, Superinstruction};
+use crate::read_input;
+
+// Behaviour of an opcode beyond the 16 built in ones. Extensions implement this for
+// a static value and are added to an `InstructionSet`, see `extensions`.
+pub trait Operation: Sync {
+    // Name in programs, e.g. "divr".
+    fn name(&self) -> &'static str;
+
+    // How an instruction uses its a and b values.
+    fn inputs(&self, a: i64, b: i64) -> [Operand; 2];
+
+    // Result for operand values, None if there is none, e.g. for division by zero.
+    fn apply(&self, a: i64, b: i64) -> Option<i64>;
+
+    // Result as a Rust expression of the operands, e.g. "a / b".
+    fn expression(&self, a: &str, b: &str) -> String;
+
+    // Result as a Rust expression of type Option<i64>, None when `apply` has no
+    // result, e.g. "i64::checked_div(a, b)".
+    fn checked_expression(&self, a: &str, b: &str) -> String {
+        format!("Some({})", self.expression(a, b))
+    }
+
+    // Whether the VM also sends the result to its output or takes it from its input.
+    fn io(&self) -> Option<Io> {
+        None
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Io {
+    // The result is written to register c and sent to the output.
+    Output,
+    // Register c is set to the next value of the input, `apply` is not used.
+    Input,
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Copy, Clone)]
+pub enum OpCode {
+    addr,
+    addi,
+    mulr,
+    muli,
+    banr,
+    bani,
+    borr,
+    bori,
+    setr,
+    seti,
+    gtir,
+    gtri,
+    gtrr,
+    eqir,
+    eqri,
+    eqrr,
+    Extension(&'static dyn Operation),
+}
+use self::OpCode::*;
+pub const ALL_OPCODES: &[OpCode] = &[
+    addr, addi, mulr, muli, banr, bani, borr, bori, setr, seti, gtir, gtri, gtrr, eqir, eqri, eqrr,
+];
+
+impl OpCode {
+    pub fn name(self) -> &'static str {
+        match self {
+            addr => "addr",
+            addi => "addi",
+            mulr => "mulr",
+            muli => "muli",
+            banr => "banr",
+            bani => "bani",
+            borr => "borr",
+            bori => "bori",
+            setr => "setr",
+            seti => "seti",
+            gtir => "gtir",
+            gtri => "gtri",
+            gtrr => "gtrr",
+            eqir => "eqir",
+            eqri => "eqri",
+            eqrr => "eqrr",
+            Extension(op) => op.name(),
+        }
+    }
+
+    // How an instruction with values a and b uses them.
+    pub fn inputs(self, a: i64, b: i64) -> [Operand; 2] {
+        let reg = |v: i64| Operand::Register(v as usize);
+        let imm = Operand::Immediate;
+        match self {
+            addr | mulr | banr | borr | gtrr | eqrr => [reg(a), reg(b)],
+            addi | muli | bani | bori | gtri | eqri => [reg(a), imm(b)],
+            gtir | eqir => [imm(a), reg(b)],
+            setr => [reg(a), Operand::Unused],
+            seti => [imm(a), Operand::Unused],
+            Extension(op) => op.inputs(a, b),
+        }
+    }
+
+    // Result for operand values a and b, wrapping around on overflow.
+    pub fn apply(self, a: i64, b: i64) -> Option<i64> {
+        Some(match self {
+            addr | addi => a.wrapping_add(b),
+            mulr | muli => a.wrapping_mul(b),
+            banr | bani => a & b,
+            borr | bori => a | b,
+            setr | seti => a,
+            gtir | gtri | gtrr => (a > b) as i64,
+            eqir | eqri | eqrr => (a == b) as i64,
+            Extension(op) => return op.apply(a, b),
+        })
+    }
+
+    // Result for operand values a and b, None on overflow.
+    pub fn checked_apply(self, a: i64, b: i64) -> Option<i64> {
+        match self {
+            addr | addi => a.checked_add(b),
+            mulr | muli => a.checked_mul(b),
+            _ => self.apply(a, b),
+        }
+    }
+
+    pub fn is_comparison(self) -> bool {
+        matches!(self, gtir | gtri | gtrr | eqir | eqri | eqrr)
+    }
+
+    // Infix operator, or None for set and extensions.
+    pub fn operator(self) -> Option<&'static str> {
+        match self {
+            addr | addi => Some("+"),
+            mulr | muli => Some("*"),
+            banr | bani => Some("&"),
+            borr | bori => Some("|"),
+            setr | seti | Extension(_) => None,
+            gtir | gtri | gtrr => Some(">"),
+            eqir | eqri | eqrr => Some("=="),
+        }
+    }
+
+    // Result as an expression of operands a and b, e.g. "if a > b { 1 } else { 0 }".
+    pub fn expression(self, a: &str, b: &str) -> String {
+        match (self, self.operator()) {
+            (Extension(op), _) => op.expression(a, b),
+            (_, None) => a.to_string(),
+            (_, Some(op)) if self.is_comparison() => {
+                format!("if {} {} {} {{ 1 }} else {{ 0 }}", a, op, b)
+            }
+            (_, Some(op)) => format!("{} {} {}", a, op, b),
+        }
+    }
+
+    // Built in opcodes in order, then extensions by name.
+    fn key(self) -> (usize, &'static str) {
+        let name = self.name();
+        let builtin = ALL_OPCODES.iter().position(|op| op.name() == name);
+        (builtin.unwrap_or(ALL_OPCODES.len()), name)
+    }
+}
+
+// Opcodes are equal by name, an `InstructionSet` keeps names unique.
+impl PartialEq for OpCode {
+    fn eq(&self, other: &OpCode) -> bool {
+        self.name() == other.name()
+    }
+}
+
+impl Eq for OpCode {}
+
+impl Hash for OpCode {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.name().hash(state)
+    }
+}
+
+impl PartialOrd for OpCode {
+    fn partial_cmp(&self, other: &OpCode) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for OpCode {
+    fn cmp(&self, other: &OpCode) -> Ordering {
+        self.key().cmp(&other.key())
+    }
+}
+
+impl fmt::Debug for OpCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl fmt::Display for OpCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for OpCode {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        InstructionSet::default().opcode(s)
+    }
+}
+
+// Opcodes a program may use, the built in ones unless extended.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InstructionSet {
+    opcodes: Vec<OpCode>,
+}
+
+impl Default for InstructionSet {
+    fn default() -> InstructionSet {
+        InstructionSet {
+            opcodes: ALL_OPCODES.to_vec(),
+        }
+    }
+}
+
+impl InstructionSet {
+    // Add an opcode, failing if its name is taken.
+    pub fn register(&mut self, op: &'static dyn Operation) -> Result<(), String> {
+        let opcode = Extension(op);
+        if self.opcodes.contains(&opcode) {
+            return Err(format!("opcode {} already exists", opcode));
+        }
+        self.opcodes.push(opcode);
+        Ok(())
+    }
+
+    pub fn opcodes(&self) -> &[OpCode] {
+        &self.opcodes
+    }
+
+    // Opcode by name, e.g. "seti".
+    pub fn opcode(&self, name: &str) -> Result<OpCode, ParseError> {
+        self.opcodes
+            .iter()
+            .find(|op| op.name() == name)
+            .copied()
+            .ok_or_else(|| ParseError::new(name, "opcode like seti"))
+    }
+
+    // Instruction like "seti 5 0 1".
+    pub fn instruction(&self, s: &str) -> Result<Instruction, ParseError> {
+        let err = || ParseError::new(s, "instruction like seti 5 0 1");
+        let mut v = s.split_whitespace();
+        let opcode = self.opcode(v.next().ok_or_else(err)?).map_err(|_| err())?;
+        let mut operand = || v.next().and_then(|d| d.parse().ok()).ok_or_else(err);
+        let a = operand()?;
+        let b = operand()?;
+        let c = operand()?;
+        if v.next().is_some() {
+            return Err(err());
+        }
+
+        Ok(Instruction { opcode, a, b, c })
+    }
+}
+
+// How an instruction uses its a and b values.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Operand {
+    Register(usize),
+    Immediate(i64),
+    Unused,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Instruction {
+    pub opcode: OpCode,
+    pub a: i64,
+    pub b: i64,
+    pub c: i64,
+}
+
+impl FromStr for Instruction {
+    type Err = ParseError;
+
+    // input: "seti 5 0 1"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        InstructionSet::default().instruction(s)
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {} {} {}", self.opcode, self.a, self.b, self.c)
+    }
+}
+
+impl Instruction {
+    pub fn inputs(&self) -> [Operand; 2] {
+        self.opcode.inputs(self.a, self.b)
+    }
+
+    // Registers read by the instruction.
+    pub fn reads(&self) -> impl Iterator<Item = usize> {
+        IntoIterator::into_iter(self.inputs()).filter_map(|op| match op {
+            Operand::Register(r) => Some(r),
+            _ => None,
+        })
+    }
+
+    // Result if the registers read are known, e.g. only the bound ip register.
+    pub fn eval(&self, register: impl Fn(usize) -> Option<i64>) -> Option<i64> {
+        let value = |op| match op {
+            Operand::Register(r) => register(r),
+            Operand::Immediate(v) => Some(v),
+            Operand::Unused => Some(0),
+        };
+        let [a, b] = self.inputs();
+        self.opcode.apply(value(a)?, value(b)?)
+    }
+
+    // Print instruction as an assignment using the given register names.
+    pub fn decompile(&self, r: &[&str]) -> String {
+        let operand = |op| match op {
+            Operand::Register(i) => r[i].to_string(),
+            Operand::Immediate(v) => v.to_string(),
+            Operand::Unused => String::new(),
+        };
+        let [a, b] = self.inputs();
+        let expression = self.opcode.expression(&operand(a), &operand(b));
+        format!("{} = {}", r[self.c as usize], expression)
+    }
+}
+
+// Register count used when parsing a program.
+pub const REGISTERS: usize = 6;
+
+// How add and multiply handle results out of the i64 range.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Arithmetic {
+    Wrapping,
+    // Stop with `VmError::Overflow`.
+    Checked,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VmError {
+    // Instruction at ip uses a register the VM doesn't have.
+    BadRegister {
+        ip: i64,
+        register: i64,
+    },
+    Overflow {
+        ip: i64,
+        instruction: Instruction,
+    },
+    // An extension has no result for the operands, e.g. division by zero.
+    Undefined {
+        ip: i64,
+        instruction: Instruction,
+    },
+    // Program still running after the step budget.
+    StepLimit(u64),
+    // Instruction at ip reads input, but there is none left.
+    NoInput {
+        ip: i64,
+    },
+    // Snapshot taken of a program with another length or number of registers.
+    BadSnapshot {
+        instructions: usize,
+        registers: usize,
+        expected: (usize, usize),
+    },
+}
+
+impl fmt::Display for VmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VmError::BadRegister { ip, register } => {
+                write!(f, "bad register {} used at instruction {}", register, ip)
+            }
+            VmError::Overflow { ip, instruction } => {
+                write!(f, "overflow in instruction {}: {}", ip, instruction)
+            }
+            VmError::Undefined { ip, instruction } => {
+                write!(f, "no result for instruction {}: {}", ip, instruction)
+            }
+            VmError::StepLimit(steps) => write!(f, "no halt after {} steps", steps),
+            VmError::NoInput { ip } => write!(f, "no input left for instruction {}", ip),
+            VmError::BadSnapshot {
+                instructions,
+                registers,
+                expected,
+            } => write!(
+                f,
+                "snapshot of {} instructions and {} registers, expected {} and {}",
+                instructions, registers, expected.0, expected.1
+            ),
+        }
+    }
+}
+
+impl error::Error for VmError {}
+
+impl From<VmError> for Error {
+    fn from(err: VmError) -> Error {
+        Error::Solve(Box::new(err))
+    }
+}
+
+// State of a running program, enough to continue it later.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    pub ip: i64,
+    pub registers: Vec<i64>,
+    pub steps: u64,
+    // Length of the program, a snapshot only continues the program it was taken of.
+    pub instructions: usize,
+    pub input: VecDeque<i64>,
+    pub output: Vec<Output>,
+}
+
+impl fmt::Display for Snapshot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "ip {} steps {} instructions {} registers",
+            self.ip, self.steps, self.instructions
+        )?;
+        for r in &self.registers {
+            write!(f, " {}", r)?;
+        }
+        write!(f, " input")?;
+        for v in &self.input {
+            write!(f, " {}", v)?;
+        }
+        write!(f, " output")?;
+        for o in &self.output {
+            write!(f, " {}:{}", o.ip, o.value)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Snapshot {
+    type Err = ParseError;
+
+    // input: "ip 28 steps 1848 instructions 31 registers 0 1 2 3 4 5 input 7 output 12:3"
+    // The queues may be left out, outputs are written as instruction:value.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let expected = "snapshot like ip 28 steps 1848 instructions 31 registers 0 1 2 3 4 5 input 7 output 12:3";
+        let words: Vec<&str> = s.split_whitespace().collect();
+        let (ip, steps, instructions, rest) = match words.as_slice() {
+            ["ip", ip, "steps", steps, "instructions", instructions, "registers", rest @ ..] => {
+                (ip, steps, instructions, rest)
+            }
+            _ => return Err(ParseError::new(s, expected)),
+        };
+        let (registers, rest) = rest.split_at(
+            rest.iter()
+                .position(|w| *w == "input" || *w == "output")
+                .unwrap_or(rest.len()),
+        );
+        let (input, output): (&[&str], &[&str]) = match rest {
+            [] => (&[], &[]),
+            ["input", rest @ ..] => match rest.iter().position(|w| *w == "output") {
+                Some(i) => (&rest[..i], &rest[i + 1..]),
+                None => (rest, &[]),
+            },
+            ["output", rest @ ..] => (&[], rest),
+            _ => return Err(ParseError::new(s, expected)),
+        };
+
+        Ok(Snapshot {
+            ip: parse_as(ip, "instruction pointer")?,
+            steps: parse_as(steps, "number of steps")?,
+            instructions: parse_as(instructions, "number of instructions")?,
+            registers: registers
+                .iter()
+                .map(|r| parse_as(r, "register value"))
+                .collect::<Result<_, _>>()?,
+            input: input
+                .iter()
+                .map(|v| parse_as(v, "input value"))
+                .collect::<Result<_, _>>()?,
+            output: output
+                .iter()
+                .map(|o| match o.split_once(':') {
+                    Some((ip, value)) => Ok(Output {
+                        ip: parse_as(ip, "instruction index")?,
+                        value: parse_as(value, "output value")?,
+                    }),
+                    None => Err(ParseError::new(o, "output like 12:3")),
+                })
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+impl Snapshot {
+    pub fn save(&self, path: &str) -> Result<(), Error> {
+        fs::write(path, format!("{}\n", self)).map_err(|err| Error::Io {
+            path: path.to_string(),
+            err,
+        })
+    }
+
+    pub fn load(path: &str) -> Result<Snapshot, Error> {
+        Ok(read_input(path)?
+            .trim()
+            .parse()
+            .map_err(|e: ParseError| e.in_file(path))?)
+    }
+}
+
+// Value sent to the output, by an output instruction or a probe.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Output {
+    // Instruction that sent it.
+    pub ip: usize,
+    pub value: i64,
+}
+
+#[derive(Debug, Clone)]
+pub struct VM {
+    pub ip: i64,
+    // Register the instruction pointer is bound to, if any.
+    pub ip_register: Option<usize>,
+    pub registers: Vec<i64>,
+    pub program: Vec<Instruction>,
+    // Loops replaced by `optimize`, by instruction index of the loop header.
+    pub optimized: Vec<Option<Superinstruction>>,
+    pub arithmetic: Arithmetic,
+    // Steps executed and the most allowed, if limited.
+    pub steps: u64,
+    pub max_steps: Option<u64>,
+    // Values for input instructions and those sent to the output, oldest first.
+    pub input: VecDeque<i64>,
+    pub output: Vec<Output>,
+    // Instructions and registers to send to the output before each execution of the
+    // instruction, e.g. to see what a comparison is given without changing the program.
+    pub probes: Vec<(usize, usize)>,
+}
+
+impl FromStr for VM {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        VM::parse(s, &InstructionSet::default())
+    }
+}
+
+impl VM {
+    // Program with optional "#ip 3" header, one instruction per line, using opcodes
+    // of the given set.
+    pub fn parse(s: &str, set: &InstructionSet) -> Result<VM, ParseError> {
+        let mut lines = s
+            .lines()
+            .enumerate()
+            .filter(|(_, l)| !l.trim().is_empty())
+            .peekable();
+        let mut ip_register = None;
+        if let Some(&(i, header)) = lines.peek() {
+            if header.trim().starts_with('#') {
+                let reg = header
+                    .trim()
+                    .strip_prefix("#ip ")
+                    .and_then(|r| r.trim().parse().ok())
+                    .filter(|&r: &usize| r < REGISTERS)
+                    .ok_or_else(|| ParseError::new(header, "ip binding like #ip 3").offset(i))?;
+                ip_register = Some(reg);
+                lines.next();
+            }
+        }
+        let lines: Vec<(usize, &str)> = lines.collect();
+        let program: Vec<Instruction> = lines
+            .iter()
+            .map(|&(i, l)| set.instruction(l.trim()).map_err(|e| e.offset(i)))
+            .collect::<Result<_, _>>()?;
+
+        VM::new(REGISTERS, ip_register, program).map_err(|e| {
+            let ip = match e {
+                VmError::BadRegister { ip, .. } => ip as usize,
+                _ => unreachable!("VM::new only checks registers"),
+            };
+            let (i, l) = lines[ip];
+            let expected = format!("instruction using registers 0 to {}", REGISTERS - 1);
+            ParseError::new(l, &expected).offset(i)
+        })
+    }
+
+    // VM at the start of program, which may only use the registers it has.
+    pub fn new(
+        n_registers: usize,
+        ip_register: Option<usize>,
+        program: Vec<Instruction>,
+    ) -> Result<VM, VmError> {
+        let vm = VM {
+            ip: 0,
+            ip_register,
+            registers: vec![0; n_registers],
+            program,
+            optimized: vec![],
+            arithmetic: Arithmetic::Checked,
+            steps: 0,
+            max_steps: None,
+            input: VecDeque::new(),
+            output: vec![],
+            probes: vec![],
+        };
+        vm.validate()?;
+        Ok(vm)
+    }
+
+    // Check that every instruction only uses existing registers, and that the ip is
+    // bound to one of them.
+    pub fn validate(&self) -> Result<(), VmError> {
+        if let Some(r) = self.ip_register.filter(|&r| r >= self.registers.len()) {
+            return Err(VmError::BadRegister {
+                ip: self.ip,
+                register: r as i64,
+            });
+        }
+        for (ip, ins) in self.program.iter().enumerate() {
+            let bad = ins
+                .reads()
+                .chain(std::iter::once(ins.c as usize))
+                .find(|&r| r >= self.registers.len());
+            if let Some(r) = bad {
+                return Err(VmError::BadRegister {
+                    ip: ip as i64,
+                    register: r as i64,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    // Replace loops with superinstructions where possible, returning how many.
+    // A superinstruction counts as one step.
+    pub fn optimize(&mut self) -> usize {
+        if let Some(ip) = self.ip_register {
+            self.optimized = optimizer::optimize(&self.program, ip);
+        }
+        for (i, s) in self.optimized.iter().enumerate() {
+            if let Some(s) = s {
+                debug!("loop at {} optimized to {:?}", i, s);
+            }
+        }
+        self.optimized.iter().filter(|s| s.is_some()).count()
+    }
+
+    fn register(&mut self, r: usize) -> Result<&mut i64, VmError> {
+        let ip = self.ip;
+        self.registers.get_mut(r).ok_or(VmError::BadRegister {
+            ip,
+            register: r as i64,
+        })
+    }
+
+    fn execute(&mut self, ins: Instruction) -> Result<(), VmError> {
+        let io = match ins.opcode {
+            Extension(op) => op.io(),
+            _ => None,
+        };
+        if io == Some(Io::Input) {
+            let ip = self.ip;
+            let value = self.input.pop_front().ok_or(VmError::NoInput { ip })?;
+            *self.register(ins.c as usize)? = value;
+            return Ok(());
+        }
+        let [a, b] = ins.inputs();
+        let mut value = |op| match op {
+            Operand::Register(r) => self.register(r).map(|v| *v),
+            Operand::Immediate(v) => Ok(v),
+            Operand::Unused => Ok(0),
+        };
+        let (a, b) = (value(a)?, value(b)?);
+        let result = match self.arithmetic {
+            Arithmetic::Wrapping => ins.opcode.apply(a, b),
+            Arithmetic::Checked => ins.opcode.checked_apply(a, b),
+        };
+        let ip = self.ip;
+        let result = result.ok_or_else(|| match ins.opcode.apply(a, b) {
+            Some(_) => VmError::Overflow {
+                ip,
+                instruction: ins,
+            },
+            None => VmError::Undefined {
+                ip,
+                instruction: ins,
+            },
+        })?;
+        *self.register(ins.c as usize)? = result;
+        if io == Some(Io::Output) {
+            self.output.push(Output {
+                ip: ip as usize,
+                value: result,
+            });
+        }
+        Ok(())
+    }
+
+    // Execute the instruction at ip, return false if the program halted.
+    pub fn step(&mut self) -> Result<bool, VmError> {
+        let ins = match self.program.get(self.ip as usize) {
+            Some(&ins) if self.ip >= 0 => ins,
+            _ => return Ok(false),
+        };
+        if let Some(max) = self.max_steps {
+            if self.steps >= max {
+                return Err(VmError::StepLimit(max));
+            }
+        }
+        self.steps += 1;
+
+        for &(at, r) in &self.probes {
+            if at as i64 == self.ip {
+                let value = *self.registers.get(r).ok_or(VmError::BadRegister {
+                    ip: self.ip,
+                    register: r as i64,
+                })?;
+                self.output.push(Output { ip: at, value });
+            }
+        }
+        if let (Some(Some(s)), Some(bound)) =
+            (self.optimized.get(self.ip as usize), self.ip_register)
+        {
+            let (exit, steps) =
+                s.execute(&mut self.registers, self.arithmetic)
+                    .map_err(|offset| {
+                        let ip = self.ip + offset as i64;
+                        VmError::Overflow {
+                            ip,
+                            instruction: self.program[ip as usize],
+                        }
+                    })?;
+            // The loop counts as the instructions it replaces, over the limit it fails
+            // with the registers as after it.
+            let steps = self.steps - 1 + steps;
+            if let Some(max) = self.max_steps.filter(|&max| steps > max) {
+                self.steps -= 1;
+                return Err(VmError::StepLimit(max));
+            }
+            self.steps = steps;
+            *self.register(bound)? = exit as i64 - 1;
+            self.ip = exit as i64;
+            return Ok(true);
+        }
+        if let Some(bound) = self.ip_register {
+            *self.register(bound)? = self.ip;
+        }
+        self.execute(ins)?;
+        if let Some(bound) = self.ip_register {
+            self.ip = *self.register(bound)?;
+        }
+        self.ip += 1;
+
+        Ok(true)
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            ip: self.ip,
+            registers: self.registers.clone(),
+            steps: self.steps,
+            instructions: self.program.len(),
+            input: self.input.clone(),
+            output: self.output.clone(),
+        }
+    }
+
+    // Continue from a snapshot of the same program, with as many registers.
+    pub fn restore(&mut self, snapshot: &Snapshot) -> Result<(), VmError> {
+        let expected = (self.program.len(), self.registers.len());
+        if (snapshot.instructions, snapshot.registers.len()) != expected {
+            return Err(VmError::BadSnapshot {
+                instructions: snapshot.instructions,
+                registers: snapshot.registers.len(),
+                expected,
+            });
+        }
+        self.ip = snapshot.ip;
+        self.registers.clone_from(&snapshot.registers);
+        self.steps = snapshot.steps;
+        self.input.clone_from(&snapshot.input);
+        self.output.clone_from(&snapshot.output);
+        Ok(())
+    }
+
+    // Run until the program halts.
+    pub fn run(&mut self) -> Result<(), VmError> {
+        while self.step()? {}
+        Ok(())
+    }
+
+    // Run, passing each output to f as it is sent instead of keeping it in `output`.
+    // Returns the value f breaks with, or None if the program halted. Outputs of the
+    // same step after the one f breaks on stay in `output`.
+    pub fn run_with<B>(
+        &mut self,
+        mut f: impl FnMut(&VM, Output) -> ControlFlow<B>,
+    ) -> Result<Option<B>, VmError> {
+        loop {
+            let running = self.step()?;
+            let mut outputs = std::mem::take(&mut self.output).into_iter();
+            while let Some(output) = outputs.next() {
+                if let ControlFlow::Break(b) = f(self, output) {
+                    self.output.extend(outputs);
+                    return Ok(Some(b));
+                }
+            }
+            if !running {
+                return Ok(None);
+            }
+        }
+    }
+
+    // Register names a, b, c, ... with the bound register called ip.
+    pub fn register_names(&self) -> Vec<String> {
+        (0..self.registers.len())
+            .map(|i| match self.ip_register {
+                Some(bound) if bound == i => "ip".to_string(),
+                _ => ((b'a' + i as u8) as char).to_string(),
+            })
+            .collect()
+    }
+
+    // Print instruction as it was using variables.
+    pub fn decompile(&self, ins: &Instruction) -> String {
+        let names = self.register_names();
+        let names: Vec<&str> = names.iter().map(String::as_str).collect();
+        ins.decompile(&names)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let vm: VM = "#ip 0\nseti 5 0 1\n\naddr 1 2 3\n".parse().unwrap();
+        assert_eq!(vm.ip_register, Some(0));
+        assert_eq!(vm.registers, vec![0; 6]);
+        assert_eq!(vm.program.len(), 2);
+        assert_eq!(vm.program[1].to_string(), "addr 1 2 3");
+
+        let vm: VM = "seti 5 0 1".parse().unwrap();
+        assert_eq!(vm.ip_register, None);
+
+        assert_eq!("#ip 6\nseti 5 0 1".parse::<VM>().unwrap_err().line, Some(1));
+        assert_eq!("#ip 1\nsetx 5 0 1".parse::<VM>().unwrap_err().line, Some(2));
+        let err = "seti 5 0 1\n\naddr 1 6 0".parse::<VM>().unwrap_err();
+        assert_eq!(err.line, Some(3));
+        assert_eq!(err.expected, "instruction using registers 0 to 5");
+        assert!("seti 5 0 -1".parse::<VM>().is_err());
+    }
+
+    #[test]
+    fn test_errors() {
+        let program = vec!["addr 0 1 2".parse().unwrap()];
+        let bad = VmError::BadRegister { ip: 0, register: 2 };
+        assert_eq!(VM::new(2, None, program.clone()).unwrap_err(), bad);
+        assert!(VM::new(2, Some(2), vec![]).is_err());
+        let mut vm = VM::new(2, None, vec![]).unwrap();
+        vm.program = program;
+        assert_eq!(vm.validate(), Err(bad.clone()));
+        assert_eq!(vm.run(), Err(bad));
+
+        // a = 1, then a *= 3 forever.
+        let program = "#ip 1\nseti 1 0 0\nmuli 0 3 0\nseti 0 0 1";
+        let mut vm: VM = program.parse().unwrap();
+        let overflow = vm.run().unwrap_err();
+        assert_eq!(
+            overflow.to_string(),
+            "overflow in instruction 1: muli 0 3 0"
+        );
+
+        let mut vm: VM = program.parse().unwrap();
+        vm.arithmetic = Arithmetic::Wrapping;
+        vm.max_steps = Some(100);
+        assert_eq!(vm.run(), Err(VmError::StepLimit(100)));
+        assert_eq!(vm.registers[0], 3i64.wrapping_pow(50));
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let mut vm: VM = "#ip 1\nseti 1 0 0\nmuli 0 3 0\nseti 0 0 1".parse().unwrap();
+        for _ in 0..5 {
+            vm.step().unwrap();
+        }
+        let snapshot = vm.snapshot();
+        assert_eq!(
+            snapshot.to_string(),
+            "ip 1 steps 5 instructions 3 registers 9 0 0 0 0 0 input output"
+        );
+        assert_eq!(snapshot.to_string().parse(), Ok(snapshot.clone()));
+        assert_eq!(
+            "ip 1 steps 5 instructions 3 registers 9 0 0 0 0 0".parse(),
+            Ok(snapshot.clone())
+        );
+
+        vm.step().unwrap();
+        let next = vm.snapshot();
+        vm.restore(&snapshot).unwrap();
+        assert_eq!(vm.snapshot(), snapshot);
+        vm.step().unwrap();
+        assert_eq!(vm.snapshot(), next);
+
+        // Snapshots of other programs would index past the registers or program.
+        let mut other: VM = "#ip 1\nseti 1 0 0\nmuli 0 3 0".parse().unwrap();
+        let err = VmError::BadSnapshot {
+            instructions: 3,
+            registers: 6,
+            expected: (2, 6),
+        };
+        assert_eq!(other.restore(&snapshot), Err(err));
+        let mut other = VM::new(4, Some(1), vm.program.clone()).unwrap();
+        assert!(other.restore(&snapshot).is_err());
+        assert_eq!(other.registers, vec![0; 4]);
+
+        assert!("ip 2 steps 5 registers 1".parse::<Snapshot>().is_err());
+        assert!("ip 2 steps -5 instructions 1 registers 1"
+            .parse::<Snapshot>()
+            .is_err());
+        assert!("ip 2 steps 5 instructions 1 registers 1 output 3"
+            .parse::<Snapshot>()
+            .is_err());
+
+        let snapshot = Snapshot {
+            input: vec![7, -1].into(),
+            output: vec![Output { ip: 12, value: 3 }],
+            ..snapshot
+        };
+        assert_eq!(
+            snapshot.to_string(),
+            "ip 1 steps 5 instructions 3 registers 9 0 0 0 0 0 input 7 -1 output 12:3"
+        );
+        assert_eq!(snapshot.to_string().parse(), Ok(snapshot));
+    }
+
+    #[test]
+    fn test_step() {
+        let mut vm = VM::new(4, None, vec!["seti 7 0 1".parse().unwrap()]).unwrap();
+        assert_eq!(vm.step(), Ok(true));
+        assert_eq!(vm.registers, vec![0, 7, 0, 0]);
+        assert_eq!(vm.step(), Ok(false));
+
+        let mut vm: VM = "#ip 0\nseti 5 0 1\nseti 6 0 2\naddi 0 1 0\naddr 1 2 3\nsetr 1 0 0\nseti 8 0 4\nseti 9 0 5"
+            .parse()
+            .unwrap();
+        vm.run().unwrap();
+        assert_eq!(vm.ip, 7);
+        assert_eq!(vm.steps, 5);
+        assert_eq!(vm.registers, vec![6, 5, 6, 0, 0, 9]);
+        assert_eq!(vm.decompile(&vm.program[3]), "d = b + c");
+
+        let mut vm = VM::new(2, None, vec!["addi 1 3 1".parse().unwrap(); 3]).unwrap();
+        vm.probes.push((1, 1));
+        vm.probes.push((2, 5));
+        let mut outputs = vec![];
+        let halted = vm.run_with(|_, o| {
+            outputs.push(o);
+            ControlFlow::<()>::Continue(())
+        });
+        assert_eq!(outputs, vec![Output { ip: 1, value: 3 }]);
+        assert_eq!(halted, Err(VmError::BadRegister { ip: 2, register: 5 }));
+
+        // Breaking on the first of two outputs keeps the second.
+        let mut vm = VM::new(2, None, vec!["addi 1 3 1".parse().unwrap(); 2]).unwrap();
+        vm.probes = vec![(1, 1), (1, 0)];
+        assert_eq!(vm.run_with(|_, o| ControlFlow::Break(o.value)), Ok(Some(3)));
+        assert_eq!(vm.output, vec![Output { ip: 1, value: 0 }]);
+        assert_eq!(
+            vm.run_with(|_, o| ControlFlow::Break(o)),
+            Ok(Some(Output { ip: 1, value: 0 }))
+        );
+        assert!(vm.output.is_empty());
+    }
+}
@@ -0,0 +1,163 @@
+use std::error;
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+
+use regex::{Captures, Regex};
+
+// Input text that doesn't match the format a parser expects.
+// Line and column are 1-based and relative to the text given to the parser,
+// callers parsing a part of a bigger input shift them with `offset`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub file: Option<String>,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    pub text: String,
+    pub expected: String,
+}
+
+impl ParseError {
+    pub fn new(text: &str, expected: &str) -> ParseError {
+        ParseError {
+            file: None,
+            line: None,
+            column: None,
+            text: text.to_string(),
+            expected: expected.to_string(),
+        }
+    }
+
+    pub fn at(mut self, line: usize, column: usize) -> ParseError {
+        self.line = Some(line);
+        self.column = Some(column);
+        self
+    }
+
+    pub fn at_column(mut self, column: usize) -> ParseError {
+        self.column = Some(column);
+        self
+    }
+
+    // Text was parsed starting after `lines` lines of the whole input.
+    pub fn offset(mut self, lines: usize) -> ParseError {
+        self.line = Some(self.line.unwrap_or(1) + lines);
+        self
+    }
+
+    pub fn in_file(mut self, file: &str) -> ParseError {
+        self.file = Some(file.to_string());
+        self
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{}:", file)?;
+        }
+        if let Some(line) = self.line {
+            write!(f, "{}:", line)?;
+        }
+        if let Some(column) = self.column {
+            write!(f, "{}:", column)?;
+        }
+        if self.file.is_some() || self.line.is_some() || self.column.is_some() {
+            write!(f, " ")?;
+        }
+        write!(f, "expected {}, found {:?}", self.expected, self.text)
+    }
+}
+
+impl error::Error for ParseError {}
+
+// Errors reported by the runner.
+#[derive(Debug)]
+pub enum Error {
+    Io { path: String, err: io::Error },
+    Parse(ParseError),
+    // Input that parsed but can't be solved, e.g. a program that fails while running.
+    Solve(Box<dyn error::Error>),
+}
+
+impl Error {
+    // Point a parse error at file, other errors don't come from a file's text.
+    pub fn in_file(self, file: &str) -> Error {
+        match self {
+            Error::Parse(err) => Error::Parse(err.in_file(file)),
+            err => err,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io { path, err } => write!(f, "{}: {}", path, err),
+            Error::Parse(err) => write!(f, "{}", err),
+            Error::Solve(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+impl From<ParseError> for Error {
+    fn from(err: ParseError) -> Error {
+        Error::Parse(err)
+    }
+}
+
+// Match the whole of s against re.
+pub fn captures<'t>(re: &Regex, s: &'t str, expected: &str) -> Result<Captures<'t>, ParseError> {
+    re.captures(s).ok_or_else(|| ParseError::new(s, expected))
+}
+
+// Parse capture group i of a line matched by `captures`,
+// pointing the error at the column of the group.
+pub fn field<T: FromStr>(
+    s: &str,
+    cap: &Captures,
+    i: usize,
+    expected: &str,
+) -> Result<T, ParseError> {
+    let m = cap.get(i).ok_or_else(|| ParseError::new(s, expected))?;
+    m.as_str()
+        .parse()
+        .map_err(|_| ParseError::new(s, expected).at_column(m.start() + 1))
+}
+
+// Parse a single value, e.g. a number separated by whitespace.
+pub fn parse_as<T: FromStr>(s: &str, expected: &str) -> Result<T, ParseError> {
+    s.parse().map_err(|_| ParseError::new(s, expected))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day23::Nanobot;
+    use crate::parse_lines;
+
+    #[test]
+    fn test_display() {
+        let err = ParseError::new("x", "number")
+            .at(3, 7)
+            .in_file("inputs/day1.txt");
+        assert_eq!(
+            err.to_string(),
+            "inputs/day1.txt:3:7: expected number, found \"x\""
+        );
+        assert_eq!(
+            ParseError::new("x", "number").to_string(),
+            "expected number, found \"x\""
+        );
+    }
+
+    #[test]
+    fn test_line_and_column() {
+        let err = parse_lines::<Nanobot>("pos=<0,0,0>, r=4\npos=<1,0,0>, r=-1").unwrap_err();
+        assert_eq!(err.line, Some(2));
+        assert_eq!(err.column, None);
+        assert_eq!(err.text, "pos=<1,0,0>, r=-1");
+    }
+}
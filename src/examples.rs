@@ -0,0 +1,116 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::answers::Answers;
+use crate::error::{Error, ParseError};
+use crate::read_input;
+
+// Example input of a day with its known answers,
+// from dir/dayN/NAME.txt and the [NAME] table of dir/dayN/answers.toml.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Example {
+    pub name: String,
+    pub input: String,
+    pub part1: Option<String>,
+    pub part2: Option<String>,
+}
+
+// Examples of day in dir, usually inputs/examples, sorted by name.
+// Days without an example directory have no examples.
+pub fn examples(dir: &str, day: u32) -> Result<Vec<Example>, Error> {
+    let day_dir = Path::new(dir).join(format!("day{}", day));
+    let io_error = |err| Error::Io {
+        path: day_dir.display().to_string(),
+        err,
+    };
+    let entries = match fs::read_dir(&day_dir) {
+        Ok(entries) => entries,
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(io_error(e)),
+    };
+
+    let answers_path = day_dir.join("answers.toml").display().to_string();
+    let answers: Answers = read_input(&answers_path)?
+        .parse()
+        .map_err(|e: ParseError| e.in_file(&answers_path))?;
+
+    let mut examples = vec![];
+    for entry in entries {
+        let path = entry.map_err(io_error)?.path();
+        if path.extension() != Some("txt".as_ref()) {
+            continue;
+        }
+        let name = path.file_stem().unwrap().to_string_lossy().to_string();
+        examples.push(Example {
+            input: read_input(&path.display().to_string())?,
+            part1: answers.get(&name, 1).map(String::from),
+            part2: answers.get(&name, 2).map(String::from),
+            name,
+        });
+    }
+    examples.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(examples)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry;
+
+    // Solve every example of every day and compare to the recorded answers.
+    // Only parts with a recorded answer are solved, examples often have different input per part.
+    #[test]
+    fn test_examples() {
+        let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/inputs/examples");
+        let mut failures = vec![];
+        let mut solved = 0;
+
+        for (day, runner) in registry() {
+            for example in examples(dir, day).unwrap() {
+                let id = format!("day{}/{}", day, example.name);
+                if example.part1.is_none() && example.part2.is_none() {
+                    failures.push(format!("{}: no recorded answers", id));
+                    continue;
+                }
+                let parsed = match runner.parse(&example.input) {
+                    Ok(parsed) => parsed,
+                    Err(e) => {
+                        failures.push(format!("{}: {}", id, e));
+                        continue;
+                    }
+                };
+                if let Some(expected) = &example.part1 {
+                    let actual = runner
+                        .part1(runner.clone_input(parsed.as_ref()))
+                        .map(|a| a.to_string())
+                        .unwrap_or_else(|e| format!("error: {}", e));
+                    if *expected != actual {
+                        failures.push(format!(
+                            "{} part1: expected {}, got {}",
+                            id, expected, actual
+                        ));
+                    }
+                    solved += 1;
+                }
+                if let Some(expected) = &example.part2 {
+                    let actual = runner
+                        .part2(parsed)
+                        .map(|a| a.map(|a| a.to_string()))
+                        .unwrap_or_else(|e| Some(format!("error: {}", e)));
+                    if Some(expected) != actual.as_ref() {
+                        failures.push(format!(
+                            "{} part2: expected {}, got {:?}",
+                            id, expected, actual
+                        ));
+                    }
+                    solved += 1;
+                }
+            }
+        }
+
+        assert!(solved > 0, "no examples found in {}", dir);
+        assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+    }
+}
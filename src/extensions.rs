@@ -0,0 +1,201 @@
+// Opcodes beyond those of 2018, added to the built in ones by `extended`:
+//
+//     divr a b c    c = a / b
+//     divi a b c    c = a / b, b immediate
+//     modr a b c    c = a % b
+//     modi a b c    c = a % b, b immediate
+//     out a _ c     c = a, sending a to the VM's output
+//     in _ _ c      c = next value of the VM's input
+//
+// Division rounds towards zero like Rust's, dividing by zero has no result.
+// Writing "out a _ a" sends a without changing any register.
+use crate::elfcode::{InstructionSet, Io, Operand, Operation};
+
+pub struct Division {
+    name: &'static str,
+    immediate: bool,
+    remainder: bool,
+}
+
+pub static DIVR: Division = Division {
+    name: "divr",
+    immediate: false,
+    remainder: false,
+};
+pub static DIVI: Division = Division {
+    name: "divi",
+    immediate: true,
+    remainder: false,
+};
+pub static MODR: Division = Division {
+    name: "modr",
+    immediate: false,
+    remainder: true,
+};
+pub static MODI: Division = Division {
+    name: "modi",
+    immediate: true,
+    remainder: true,
+};
+
+impl Operation for Division {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn inputs(&self, a: i64, b: i64) -> [Operand; 2] {
+        let b = if self.immediate {
+            Operand::Immediate(b)
+        } else {
+            Operand::Register(b as usize)
+        };
+        [Operand::Register(a as usize), b]
+    }
+
+    fn apply(&self, a: i64, b: i64) -> Option<i64> {
+        if self.remainder {
+            a.checked_rem(b)
+        } else {
+            a.checked_div(b)
+        }
+    }
+
+    fn expression(&self, a: &str, b: &str) -> String {
+        let op = if self.remainder { "%" } else { "/" };
+        format!("{} {} {}", a, op, b)
+    }
+
+    fn checked_expression(&self, a: &str, b: &str) -> String {
+        let op = if self.remainder { "rem" } else { "div" };
+        format!("i64::checked_{}({}, {})", op, a, b)
+    }
+}
+
+pub struct Port {
+    name: &'static str,
+    io: Io,
+}
+
+pub static OUT: Port = Port {
+    name: "out",
+    io: Io::Output,
+};
+pub static IN: Port = Port {
+    name: "in",
+    io: Io::Input,
+};
+
+impl Operation for Port {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn inputs(&self, a: i64, _: i64) -> [Operand; 2] {
+        match self.io {
+            Io::Output => [Operand::Register(a as usize), Operand::Unused],
+            Io::Input => [Operand::Unused, Operand::Unused],
+        }
+    }
+
+    // Input depends on the VM, so has no result from operands alone.
+    fn apply(&self, a: i64, _: i64) -> Option<i64> {
+        match self.io {
+            Io::Output => Some(a),
+            Io::Input => None,
+        }
+    }
+
+    fn expression(&self, a: &str, _: &str) -> String {
+        match self.io {
+            Io::Output => format!("output({})", a),
+            Io::Input => "input()".to_string(),
+        }
+    }
+
+    fn io(&self) -> Option<Io> {
+        Some(self.io)
+    }
+}
+
+// Built in opcodes with the ones above.
+pub fn extended() -> InstructionSet {
+    let mut set = InstructionSet::default();
+    let ops: [&'static dyn Operation; 6] = [&DIVR, &DIVI, &MODR, &MODI, &OUT, &IN];
+    for op in IntoIterator::into_iter(ops) {
+        set.register(op).unwrap();
+    }
+    set
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elfcode::{Output, VmError, VM};
+    use std::ops::ControlFlow;
+
+    // Sum of the decimal digits of a.
+    const PROGRAM: &str = "#ip 5
+modi 0 10 2
+addr 1 2 1
+divi 0 10 0
+gtri 0 0 3
+addr 3 5 5
+seti 99 0 5
+seti -1 0 5";
+
+    #[test]
+    fn test_extended() {
+        assert!(PROGRAM.parse::<VM>().is_err());
+        let set = extended();
+        assert!(set.clone().register(&MODR).is_err());
+
+        let mut vm = VM::parse(PROGRAM, &set).unwrap();
+        vm.registers[0] = 90210;
+        vm.run().unwrap();
+        assert_eq!(vm.registers[1], 9 + 2 + 1);
+        assert_eq!(vm.decompile(&vm.program[2]), "a = a / 10");
+        assert_eq!(vm.program[0].to_string(), "modi 0 10 2");
+
+        let mut vm = VM::parse("divr 0 1 2", &set).unwrap();
+        assert!(matches!(vm.run(), Err(VmError::Undefined { ip: 0, .. })));
+    }
+
+    // Output the digits of each input, least significant first.
+    const DIGITS: &str = "#ip 5
+in 0 0 0
+modi 0 10 1
+out 1 0 1
+divi 0 10 0
+gtri 0 0 2
+addr 2 5 5
+seti -1 0 5
+seti 0 0 5";
+
+    #[test]
+    fn test_io() {
+        let mut vm = VM::parse(DIGITS, &extended()).unwrap();
+        assert_eq!(vm.decompile(&vm.program[2]), "b = output(b)");
+        vm.input.extend(&[42, 7]);
+        assert_eq!(vm.run(), Err(VmError::NoInput { ip: 0 }));
+        let values: Vec<i64> = vm.output.iter().map(|o| o.value).collect();
+        assert_eq!(values, vec![2, 4, 7]);
+        assert_eq!(vm.output[0], Output { ip: 2, value: 2 });
+
+        let mut vm = VM::parse(DIGITS, &extended()).unwrap();
+        vm.input.extend(&[123, 45]);
+        let mut values = vec![];
+        let stop = vm.run_with(|vm, o| {
+            values.push(o.value);
+            if vm.registers[0] < 10 {
+                ControlFlow::Break(vm.steps)
+            } else {
+                ControlFlow::Continue(())
+            }
+        });
+        assert_eq!(stop, Ok(Some(15)));
+        assert_eq!(values, vec![3, 2, 1]);
+        assert!(vm.output.is_empty());
+        assert_eq!(vm.run(), Err(VmError::NoInput { ip: 0 }));
+        assert_eq!(vm.output.len(), 2);
+    }
+}
@@ -0,0 +1,198 @@
+// Halting analysis for programs that only stop when a register equals register 0,
+// the input, like day 21.
+//
+// The program is searched for "x = if r == a { 1 } else { 0 }" followed by a branch to
+// the end of the program, with register 0 only read there. Running it with an input
+// that never matches then gives the values it would halt for, in order.
+use std::collections::HashSet;
+use std::error;
+use std::fmt;
+use std::ops::ControlFlow;
+
+use crate::decompiler::{Cfg, Exit};
+use crate::elfcode::OpCode::eqrr;
+use crate::elfcode::{VmError, VM};
+use crate::error::Error;
+
+// Instructions, counting those of optimized loops, day 21 takes about 2.5 billion.
+pub const MAX_STEPS: u64 = 10_000_000_000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Halting {
+    // Instruction comparing register 0 with `register`.
+    pub compare: usize,
+    pub register: usize,
+    // Input halting after the fewest and the most instructions.
+    pub fastest: i64,
+    pub slowest: i64,
+    // Number of different inputs that halt.
+    pub candidates: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HaltingError {
+    // No comparison of register 0 deciding whether the program halts.
+    NoHaltCheck,
+    // The program halted for an input never compared.
+    Halted,
+    StepLimit(u64),
+    Vm(VmError),
+}
+
+impl fmt::Display for HaltingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HaltingError::NoHaltCheck => write!(
+                f,
+                "no comparison with register 0 followed by a jump to the end of the program"
+            ),
+            HaltingError::Halted => write!(f, "program halted without matching register 0"),
+            HaltingError::StepLimit(steps) => {
+                write!(f, "no repeating state after {} steps", steps)
+            }
+            HaltingError::Vm(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl error::Error for HaltingError {}
+
+impl From<HaltingError> for Error {
+    fn from(err: HaltingError) -> Error {
+        Error::Solve(Box::new(err))
+    }
+}
+
+impl From<VmError> for HaltingError {
+    fn from(err: VmError) -> HaltingError {
+        HaltingError::Vm(err)
+    }
+}
+
+// Whether jumping to instruction t halts, possibly through blocks that only jump.
+fn halts(vm: &VM, cfg: &Cfg, mut t: i64) -> bool {
+    for _ in 0..=cfg.blocks.len() {
+        if t < 0 || t >= vm.program.len() as i64 {
+            return true;
+        }
+        match cfg.blocks.iter().find(|b| b.start as i64 == t) {
+            Some(b) if b.end == b.start + 1 => match b.exit {
+                Exit::Jump(next) if next != b.end as i64 => t = next,
+                _ => return false,
+            },
+            _ => return false,
+        }
+    }
+    false
+}
+
+// Instruction comparing register 0 with another register, returning both.
+pub fn find_halt_check(vm: &VM) -> Option<(usize, usize)> {
+    let reads_a = |i: usize| vm.program[i].reads().any(|r| r == 0);
+    let a_written = vm.program.iter().any(|ins| ins.c == 0);
+    let cfg = Cfg::new(vm);
+
+    cfg.blocks.iter().find_map(|b| match b.exit {
+        Exit::Branch { compare, then, .. } if !a_written && halts(vm, &cfg, then) => {
+            let ins = &vm.program[compare];
+            let register = match (ins.a, ins.b) {
+                (0, r) | (r, 0) if r != 0 => r as usize,
+                _ => return None,
+            };
+            let only_read = (0..vm.program.len()).all(|i| i == compare || !reads_a(i));
+            if ins.opcode == eqrr && Some(register) != vm.ip_register && only_read {
+                Some((compare, register))
+            } else {
+                None
+            }
+        }
+        _ => None,
+    })
+}
+
+// Run the program with a probe on the halt check until the state there repeats,
+// collecting the compared values. Loops the optimizer knows run natively.
+pub fn analyze(vm: &VM, max_steps: u64) -> Result<Halting, HaltingError> {
+    let (compare, register) = find_halt_check(vm).ok_or(HaltingError::NoHaltCheck)?;
+    let mut vm = vm.clone();
+    vm.registers[0] = i64::MIN;
+    vm.optimize();
+    vm.max_steps = Some(vm.steps + max_steps);
+    vm.probes.push((compare, register));
+
+    let mut states = HashSet::new();
+    let mut seen = HashSet::new();
+    let mut values = vec![];
+    let repeated = vm.run_with(|vm, output| {
+        if !states.insert(vm.registers.clone()) {
+            return ControlFlow::Break(());
+        }
+        if seen.insert(output.value) {
+            values.push(output.value);
+        }
+        ControlFlow::Continue(())
+    });
+    match repeated {
+        Ok(Some(())) => {
+            info!(
+                "state at {} repeats after {} values, {} different, {} steps",
+                compare,
+                states.len(),
+                values.len(),
+                vm.steps
+            );
+            Ok(Halting {
+                compare,
+                register,
+                fastest: values[0],
+                slowest: *values.last().unwrap(),
+                candidates: values.len(),
+            })
+        }
+        Ok(None) => Err(HaltingError::Halted),
+        Err(VmError::StepLimit(_)) => Err(HaltingError::StepLimit(max_steps)),
+        Err(err) => Err(err.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // b = (b * 3 + 1) % 7 from 0 until b == a, repeating 1 4 6 5 2 0 1.
+    const PROGRAM: &str = "#ip 5
+muli 1 3 1
+addi 1 1 1
+gtri 1 6 2
+addr 2 5 5
+seti 6 0 5
+addi 1 -7 1
+seti 1 0 5
+eqrr 1 0 2
+addr 2 5 5
+seti -1 0 5";
+
+    #[test]
+    fn test_analyze() {
+        let vm: VM = PROGRAM.parse().unwrap();
+        assert_eq!(find_halt_check(&vm), Some((7, 1)));
+
+        let halting = analyze(&vm, 10_000).unwrap();
+        assert_eq!(
+            halting,
+            Halting {
+                compare: 7,
+                register: 1,
+                fastest: 1,
+                slowest: 0,
+                candidates: 6,
+            }
+        );
+        assert_eq!(analyze(&vm, 10), Err(HaltingError::StepLimit(10)));
+
+        let vm: VM = "#ip 5\nseti 1 0 1\neqrr 1 0 2\naddr 2 5 5\nseti 0 0 0"
+            .parse()
+            .unwrap();
+        assert_eq!(find_halt_check(&vm), None);
+    }
+}
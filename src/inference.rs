@@ -0,0 +1,217 @@
+// Inference of the opcode behind each opcode number from observed samples.
+//
+// Every sample narrows the opcodes its number can stand for. The assignment is then
+// a bipartite matching of numbers to distinct opcodes: it fails if there is none and
+// is ambiguous if a number can take more than one opcode in some matching.
+use std::collections::{BTreeMap, BTreeSet};
+use std::error;
+use std::fmt;
+
+use crate::elfcode::{Instruction, OpCode, ALL_OPCODES};
+use crate::error::Error;
+
+pub type Candidates = BTreeMap<i64, BTreeSet<OpCode>>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InferenceError {
+    // No assignment of distinct opcodes agrees with every sample.
+    Contradictory(Candidates),
+    // More than one assignment agrees, with the opcodes each undecided number can take.
+    Ambiguous(Candidates),
+}
+
+fn write_candidates(f: &mut fmt::Formatter, candidates: &Candidates) -> fmt::Result {
+    for (number, ops) in candidates {
+        let ops: Vec<String> = ops.iter().map(|op| op.to_string()).collect();
+        write!(f, "\n  {:>2}: {{{}}}", number, ops.join(", "))?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for InferenceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InferenceError::Contradictory(candidates) => {
+                write!(f, "contradictory samples, candidate opcodes:")?;
+                write_candidates(f, candidates)
+            }
+            InferenceError::Ambiguous(candidates) => {
+                write!(f, "ambiguous samples, undecided opcodes:")?;
+                write_candidates(f, candidates)
+            }
+        }
+    }
+}
+
+impl error::Error for InferenceError {}
+
+impl From<InferenceError> for Error {
+    fn from(err: InferenceError) -> Error {
+        Error::Solve(Box::new(err))
+    }
+}
+
+// Opcodes for which instruction "op a b c" turns registers before into after.
+// Reads and writes of registers out of range never match.
+pub fn matching_opcodes(operands: [i64; 3], before: &[i64], after: &[i64]) -> BTreeSet<OpCode> {
+    let [a, b, c] = operands;
+    let c = c as usize;
+    if c >= before.len() || before.len() != after.len() {
+        return BTreeSet::new();
+    }
+    let unchanged = (0..before.len()).all(|r| r == c || before[r] == after[r]);
+
+    ALL_OPCODES
+        .iter()
+        .copied()
+        .filter(|&opcode| {
+            let ins = Instruction {
+                opcode,
+                a,
+                b,
+                c: c as i64,
+            };
+            unchanged && ins.eval(|r| before.get(r).copied()) == Some(after[c])
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Inference {
+    pub candidates: Candidates,
+}
+
+impl Inference {
+    pub fn new() -> Inference {
+        Inference::default()
+    }
+
+    // Narrow number to the opcodes matching a sample.
+    pub fn observe(&mut self, number: i64, matching: &BTreeSet<OpCode>) {
+        let ops = self
+            .candidates
+            .entry(number)
+            .or_insert_with(|| matching.clone());
+        ops.retain(|op| matching.contains(op));
+    }
+
+    // Opcode of every observed number.
+    pub fn solve(&self) -> Result<BTreeMap<i64, OpCode>, InferenceError> {
+        let assignment = assign(&self.candidates)
+            .ok_or_else(|| InferenceError::Contradictory(self.candidates.clone()))?;
+
+        // Alternatives of a number are opcodes still leaving a matching for the others.
+        let mut undecided = Candidates::new();
+        for (&number, ops) in &self.candidates {
+            let possible: BTreeSet<OpCode> = ops
+                .iter()
+                .copied()
+                .filter(|&op| {
+                    op == assignment[&number] || assign_with(&self.candidates, number, op)
+                })
+                .collect();
+            if possible.len() > 1 {
+                undecided.insert(number, possible);
+            }
+        }
+        if !undecided.is_empty() {
+            return Err(InferenceError::Ambiguous(undecided));
+        }
+
+        debug!("opcode assignment: {:?}", assignment);
+        Ok(assignment)
+    }
+}
+
+// Whether there is a matching assigning op to number.
+fn assign_with(candidates: &Candidates, number: i64, op: OpCode) -> bool {
+    let rest: Candidates = candidates
+        .iter()
+        .filter(|&(&n, _)| n != number)
+        .map(|(&n, ops)| (n, ops.iter().copied().filter(|&o| o != op).collect()))
+        .collect();
+    assign(&rest).is_some()
+}
+
+// Matching of every number to a distinct opcode by augmenting paths.
+fn assign(candidates: &Candidates) -> Option<BTreeMap<i64, OpCode>> {
+    let mut owner: BTreeMap<OpCode, i64> = BTreeMap::new();
+    for &number in candidates.keys() {
+        let mut visited = BTreeSet::new();
+        if !augment(candidates, number, &mut owner, &mut visited) {
+            return None;
+        }
+    }
+    Some(owner.into_iter().map(|(op, number)| (number, op)).collect())
+}
+
+// Find an opcode for number, moving numbers already assigned to other candidates.
+fn augment(
+    candidates: &Candidates,
+    number: i64,
+    owner: &mut BTreeMap<OpCode, i64>,
+    visited: &mut BTreeSet<OpCode>,
+) -> bool {
+    for &op in &candidates[&number] {
+        if !visited.insert(op) {
+            continue;
+        }
+        let free = match owner.get(&op) {
+            None => true,
+            Some(&other) => augment(candidates, other, owner, visited),
+        };
+        if free {
+            owner.insert(op, number);
+            return true;
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elfcode::OpCode::*;
+
+    fn inference(candidates: &[(i64, &[OpCode])]) -> Inference {
+        let mut inference = Inference::new();
+        for &(number, ops) in candidates {
+            inference.observe(number, &ops.iter().copied().collect());
+        }
+        inference
+    }
+
+    #[test]
+    fn test_matching_opcodes() {
+        let ops = matching_opcodes([2, 1, 2], &[3, 2, 1, 1], &[3, 2, 2, 1]);
+        assert_eq!(ops, [addi, mulr, seti].iter().copied().collect());
+        assert!(matching_opcodes([0, 0, 4], &[1, 2, 3, 4], &[1, 2, 3, 4]).is_empty());
+    }
+
+    #[test]
+    fn test_solve() {
+        // Needs moving 1 away from addr when assigning 2, no singleton to start from.
+        let i = inference(&[
+            (1, &[addr, addi]),
+            (2, &[addr, mulr]),
+            (3, &[addi, mulr, seti]),
+            (3, &[mulr]),
+        ]);
+        let expected: BTreeMap<_, _> = vec![(1, addi), (2, addr), (3, mulr)].into_iter().collect();
+        assert_eq!(i.solve(), Ok(expected));
+
+        let i = inference(&[(1, &[addr, addi]), (2, &[addr, addi]), (3, &[seti])]);
+        let undecided = inference(&[(1, &[addr, addi]), (2, &[addr, addi])]).candidates;
+        assert_eq!(i.solve(), Err(InferenceError::Ambiguous(undecided)));
+
+        let i = inference(&[(1, &[addr]), (2, &[addr, addi]), (2, &[seti, addr])]);
+        assert_eq!(
+            i.solve(),
+            Err(InferenceError::Contradictory(i.candidates.clone()))
+        );
+        assert_eq!(
+            i.solve().unwrap_err().to_string(),
+            "contradictory samples, candidate opcodes:\n   1: {addr}\n   2: {addr}"
+        );
+    }
+}
@@ -0,0 +1,73 @@
+// Just enough JSON writing for machine readable output, values are written by hand.
+
+use crate::answer::Answer;
+
+// s as a JSON string literal.
+pub fn quote(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+// Answer as a JSON value: integers as numbers, coordinates as arrays of numbers
+// and text and image paths as strings.
+pub fn answer(a: &Answer) -> String {
+    match a {
+        Answer::Integer(n) => n.to_string(),
+        Answer::Text(s) | Answer::Image(s) => quote(s),
+        Answer::Coordinates(c) => array(c.iter().map(|v| v.to_string())),
+    }
+}
+
+// Name of the kind of answer, telling how to read the value from `answer`.
+pub fn answer_type(a: &Answer) -> &'static str {
+    match a {
+        Answer::Integer(_) => "integer",
+        Answer::Text(_) => "text",
+        Answer::Coordinates(_) => "coordinates",
+        Answer::Image(_) => "image",
+    }
+}
+
+// Array of JSON values.
+pub fn array<I: IntoIterator<Item = String>>(values: I) -> String {
+    format!("[{}]", values.into_iter().collect::<Vec<_>>().join(","))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quote() {
+        assert_eq!(quote("abc"), r#""abc""#);
+        assert_eq!(quote("a\"b\\c\n"), r#""a\"b\\c\n""#);
+        assert_eq!(quote("\u{1}"), r#""\u0001""#);
+    }
+
+    #[test]
+    fn test_answer() {
+        assert_eq!(answer(&Answer::Integer(-12)), "-12");
+        assert_eq!(
+            answer(&Answer::Text("0124515891".to_string())),
+            r#""0124515891""#
+        );
+        assert_eq!(answer(&Answer::Coordinates(vec![245, 14])), "[245,14]");
+        assert_eq!(
+            answer_type(&Answer::Coordinates(vec![245, 14])),
+            "coordinates"
+        );
+    }
+}
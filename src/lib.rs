@@ -0,0 +1,118 @@
+extern crate regex;
+#[macro_use]
+extern crate lazy_static;
+#[macro_use]
+extern crate log;
+extern crate image;
+
+use std::collections::BTreeMap;
+use std::io::Read;
+
+pub mod analysis;
+pub mod answer;
+pub mod answers;
+pub mod assembler;
+pub mod bench;
+pub mod debugger;
+pub mod decompiler;
+pub mod elfcode;
+pub mod error;
+pub mod examples;
+pub mod extensions;
+pub mod halting;
+pub mod inference;
+pub mod json;
+pub mod logger;
+pub mod optimizer;
+pub mod profiler;
+pub mod solution;
+pub mod transpiler;
+
+pub mod day1;
+pub mod day10;
+pub mod day11;
+pub mod day12;
+pub mod day13;
+pub mod day14;
+pub mod day15;
+pub mod day16;
+pub mod day17;
+pub mod day18;
+pub mod day19;
+pub mod day2;
+pub mod day20;
+pub mod day21;
+pub mod day22;
+pub mod day23;
+pub mod day24;
+pub mod day25;
+pub mod day3;
+pub mod day4;
+pub mod day5;
+pub mod day6;
+pub mod day7;
+pub mod day8;
+pub mod day9;
+
+pub use crate::answer::Answer;
+pub use crate::error::{Error, ParseError};
+pub use crate::solution::{run, Report, Runner, Solution};
+
+// Parse every line of input as T.
+pub fn parse_lines<T>(s: &str) -> Result<Vec<T>, ParseError>
+where
+    T: std::str::FromStr<Err = ParseError>,
+{
+    s.lines()
+        .enumerate()
+        .map(|(i, v)| v.parse::<T>().map_err(|e| e.offset(i)))
+        .collect()
+}
+
+// Read input unmodified, from stdin if path is "-".
+pub fn read_input(path: &str) -> Result<String, Error> {
+    let io_error = |err| Error::Io {
+        path: path.to_string(),
+        err,
+    };
+    if path == "-" {
+        let mut input = String::new();
+        std::io::stdin()
+            .read_to_string(&mut input)
+            .map_err(io_error)?;
+        Ok(input)
+    } else {
+        std::fs::read_to_string(path).map_err(io_error)
+    }
+}
+
+// All solved days keyed by day number.
+pub fn registry() -> BTreeMap<u32, Box<dyn Runner>> {
+    let mut days: BTreeMap<u32, Box<dyn Runner>> = BTreeMap::new();
+    days.insert(1, Box::new(day1::Day1));
+    days.insert(2, Box::new(day2::Day2));
+    days.insert(3, Box::new(day3::Day3));
+    days.insert(4, Box::new(day4::Day4));
+    days.insert(5, Box::new(day5::Day5));
+    days.insert(6, Box::new(day6::Day6));
+    days.insert(7, Box::new(day7::Day7));
+    days.insert(8, Box::new(day8::Day8));
+    days.insert(9, Box::new(day9::Day9));
+    days.insert(10, Box::new(day10::Day10));
+    days.insert(11, Box::new(day11::Day11));
+    days.insert(12, Box::new(day12::Day12));
+    days.insert(13, Box::new(day13::Day13));
+    days.insert(14, Box::new(day14::Day14));
+    days.insert(15, Box::new(day15::Day15));
+    days.insert(16, Box::new(day16::Day16));
+    days.insert(17, Box::new(day17::Day17));
+    days.insert(18, Box::new(day18::Day18));
+    days.insert(19, Box::new(day19::Day19));
+    days.insert(20, Box::new(day20::Day20));
+    days.insert(21, Box::new(day21::Day21));
+    days.insert(22, Box::new(day22::Day22));
+    days.insert(23, Box::new(day23::Day23));
+    days.insert(24, Box::new(day24::Day24));
+    days.insert(25, Box::new(day25::Day25));
+    days
+}
@@ -0,0 +1,132 @@
+use std::str::FromStr;
+
+use log::{LevelFilter, Log, Metadata, Record};
+
+use crate::error::ParseError;
+
+// Level for records of one module, overriding the verbosity,
+// e.g. "day15=trace", or "day15" for debug.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Filter {
+    pub module: String,
+    pub level: LevelFilter,
+}
+
+impl Filter {
+    // Module names match the full target or its last part, e.g. "day15".
+    fn matches(&self, target: &str) -> bool {
+        self.module == target || target.rsplit("::").next() == Some(self.module.as_str())
+    }
+}
+
+impl FromStr for Filter {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (module, level) = match s.split_once('=') {
+            Some((module, level)) => {
+                let level = level.parse().map_err(|_| {
+                    ParseError::new(level, "level like off, error, warn, info, debug or trace")
+                        .at_column(module.len() + 2)
+                })?;
+                (module, level)
+            }
+            None => (s, LevelFilter::Debug),
+        };
+        if module.is_empty() {
+            return Err(ParseError::new(s, "module like day15"));
+        }
+        Ok(Filter {
+            module: module.to_string(),
+            level,
+        })
+    }
+}
+
+// Writes log records of solvers to stderr, tagged with the day they come from,
+// so that stdout only has answers.
+struct Logger {
+    level: LevelFilter,
+    filters: Vec<Filter>,
+}
+
+impl Log for Logger {
+    // The last filter matching the target decides, the verbosity if none does.
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        let level = self
+            .filters
+            .iter()
+            .rev()
+            .find(|f| f.matches(metadata.target()))
+            .map_or(self.level, |f| f.level);
+        metadata.level() <= level
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            let target = record.target();
+            let day = target.rsplit("::").next().unwrap_or(target);
+            eprintln!("[{} {}] {}", record.level(), day, record.args());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+// Level for a verbosity given as count of -v minus count of -q flags:
+// -q: nothing, default: warnings, -v: info, -vv: debug, -vvv: trace.
+pub fn level(verbosity: i32) -> LevelFilter {
+    match verbosity {
+        v if v < 0 => LevelFilter::Off,
+        0 => LevelFilter::Warn,
+        1 => LevelFilter::Info,
+        2 => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    }
+}
+
+// Route log records to stderr, can be called only once.
+pub fn init(verbosity: i32, filters: Vec<Filter>) {
+    let level = level(verbosity);
+    let max = filters.iter().map(|f| f.level).fold(level, Ord::max);
+    let logger = Box::leak(Box::new(Logger { level, filters }));
+    log::set_logger(logger).expect("logger initialized twice");
+    log::set_max_level(max);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use log::Level;
+
+    #[test]
+    fn test_level() {
+        assert_eq!(level(-2), LevelFilter::Off);
+        assert_eq!(level(0), LevelFilter::Warn);
+        assert_eq!(level(2), LevelFilter::Debug);
+        assert_eq!(level(5), LevelFilter::Trace);
+    }
+
+    #[test]
+    fn test_filters() {
+        let filters = vec![
+            "day15".parse().unwrap(),
+            "aoc_2018::day16=off".parse().unwrap(),
+        ];
+        let logger = Logger {
+            level: LevelFilter::Warn,
+            filters,
+        };
+        let enabled = |level, target| {
+            logger.enabled(&Metadata::builder().level(level).target(target).build())
+        };
+        assert!(enabled(Level::Debug, "aoc_2018::day15"));
+        assert!(!enabled(Level::Trace, "aoc_2018::day15"));
+        assert!(!enabled(Level::Debug, "aoc_2018::day1"));
+        assert!(enabled(Level::Warn, "aoc_2018::day1"));
+        assert!(!enabled(Level::Error, "aoc_2018::day16"));
+
+        assert!("day15=loud".parse::<Filter>().is_err());
+        assert!("=debug".parse::<Filter>().is_err());
+    }
+}
@@ -1,211 +1,964 @@
-extern crate regex;
-#[macro_use]
-extern crate lazy_static;
-extern crate image;
-
-mod day1;
-mod day10;
-mod day11;
-mod day12;
-mod day13;
-mod day14;
-mod day15;
-mod day16;
-mod day17;
-mod day18;
-mod day19;
-mod day2;
-mod day20;
-mod day21;
-mod day22;
-mod day23;
-mod day24;
-mod day25;
-mod day3;
-mod day4;
-mod day5;
-mod day6;
-mod day7;
-mod day8;
-mod day9;
-
-pub fn read_and_parse_input<T>(file: &str) -> Vec<T>
-where
-    T: std::str::FromStr,
-    <T as std::str::FromStr>::Err: std::fmt::Debug,
-{
-    std::fs::read_to_string(format!("inputs/{}", file))
-        .unwrap()
-        .lines()
-        .map(|v| v.parse::<T>().unwrap())
-        .collect()
-}
-
-// Read input unmodified.
-pub fn read_input(file: &str) -> String {
-    std::fs::read_to_string(format!("inputs/{}", file))
-        .unwrap()
-        .to_string()
+use std::io::{self, BufRead, Write};
+use std::process;
+use std::time::Duration;
+
+use aoc_2018::analysis::{analyze, Interval};
+use aoc_2018::answers::Answers;
+use aoc_2018::assembler::assemble_with;
+use aoc_2018::bench::{bench, Stage, Stats};
+use aoc_2018::day16::{self, Manual};
+use aoc_2018::debugger::{Command as DebugCommand, Debugger};
+use aoc_2018::decompiler::decompile;
+use aoc_2018::elfcode::{InstructionSet, VM};
+use aoc_2018::extensions;
+use aoc_2018::halting;
+use aoc_2018::profiler::profile;
+use aoc_2018::transpiler::transpile_program;
+use aoc_2018::{json, logger, read_input, registry, run, Answer, Error, ParseError, Report};
+
+const USAGE: &str = "usage:
+  aoc-2018 <day|all|first-last> [--input <path>|-] [--format text|json]
+  aoc-2018 bench <day> [--part N] [--iterations K] [--warmup W] [--input <path>|-] [--format text|csv|json]
+  aoc-2018 verify [day|all|first-last] [--answers <path>]
+  aoc-2018 assemble <path>|- [--extended]
+  aoc-2018 debug <day> [--input <path>] [--extended]
+  aoc-2018 decompile <day> [--input <path>|-] [--extended]
+  aoc-2018 transpile <day> [--input <path>|-] [--extended]
+  aoc-2018 halting <day> [--input <path>|-] [--extended]
+  aoc-2018 analyze <day> [--input <path>|-] [--registers a,b,...] [--extended]
+  aoc-2018 profile <day> [--input <path>|-] [--steps N] [--registers a,b,...] [--extended]
+options for every command:
+  -q, -v, -vv, -vvv  less or more details of solving on stderr
+  --log <module>[=level]  details of one module only, e.g. --log day15 or --log day15=trace";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Text,
+    Csv,
+    Json,
 }
 
-fn main() {
-    let day = std::env::args()
-        .nth(1)
-        .expect("missing day argument")
-        .parse::<u64>()
-        .expect("day must be integer");
-
-    match day {
-        1 => {
-            println!(
-                "day1 part1: {}",
-                day1::solve1(read_and_parse_input("day1.txt"))
-            );
-            println!(
-                "day1 part2: {}",
-                day1::solve2(read_and_parse_input("day1.txt"))
-            );
+fn parse_format(
+    args: &mut impl Iterator<Item = String>,
+    allowed: &[Format],
+) -> Result<Format, String> {
+    let name = option_value::<String>("--format", args)?;
+    let format = match name.as_str() {
+        "text" => Format::Text,
+        "csv" => Format::Csv,
+        "json" => Format::Json,
+        _ => return Err(format!("unknown format {:?}", name)),
+    };
+    if !allowed.contains(&format) {
+        return Err(format!(
+            "format {:?} is not supported by this command",
+            name
+        ));
+    }
+    Ok(format)
+}
+
+enum Command {
+    Run {
+        days: Vec<u32>,
+        input: Option<String>,
+        format: Format,
+    },
+    Bench {
+        day: u32,
+        input: Option<String>,
+        stages: Vec<Stage>,
+        iterations: usize,
+        warmup: usize,
+        format: Format,
+    },
+    Verify {
+        days: Vec<u32>,
+        answers: String,
+    },
+    Debug {
+        day: u32,
+        input: Option<String>,
+        extended: bool,
+    },
+    Decompile {
+        day: u32,
+        input: Option<String>,
+        extended: bool,
+    },
+    Transpile {
+        day: u32,
+        input: Option<String>,
+        extended: bool,
+    },
+    Halting {
+        day: u32,
+        input: Option<String>,
+        extended: bool,
+    },
+    Assemble {
+        source: String,
+        extended: bool,
+    },
+    Profile {
+        day: u32,
+        input: Option<String>,
+        extended: bool,
+        steps: u64,
+        registers: Vec<i64>,
+    },
+    Analyze {
+        day: u32,
+        input: Option<String>,
+        extended: bool,
+        // Initial register values, None for any value.
+        registers: Vec<Option<i64>>,
+    },
+}
+
+// Parse "all", a single day like "4" or a range like "10-15".
+fn parse_days(arg: &str) -> Result<Vec<u32>, String> {
+    let day = |d: &str| {
+        d.parse::<u32>().map_err(|_| {
+            format!(
+                "day must be a number, \"all\" or a range like 10-15, got {:?}",
+                arg
+            )
+        })
+    };
+    if arg == "all" {
+        return Ok(registry().keys().cloned().collect());
+    }
+    match arg.find('-') {
+        Some(i) => {
+            let (first, last) = (day(&arg[..i])?, day(&arg[i + 1..])?);
+            if first > last {
+                return Err(format!("empty range {:?}", arg));
+            }
+            Ok((first..=last).collect())
         }
-        2 => {
-            println!(
-                "day2 part1: {}",
-                day2::solve1(read_and_parse_input("day2.txt"))
-            );
-            println!(
-                "day2 part2: {}",
-                day2::solve2(read_and_parse_input("day2.txt"))
-            );
+        None => Ok(vec![day(arg)?]),
+    }
+}
+
+fn check_registered(days: &[u32]) -> Result<(), String> {
+    let registered = registry();
+    match days.iter().find(|d| !registered.contains_key(d)) {
+        Some(day) => Err(format!("no solution for day {}", day)),
+        None => Ok(()),
+    }
+}
+
+// Value of an option taking an argument, e.g. "--iterations 10".
+fn option_value<T: std::str::FromStr>(
+    name: &str,
+    args: &mut impl Iterator<Item = String>,
+) -> Result<T, String> {
+    let value = args
+        .next()
+        .ok_or_else(|| format!("{} requires a value", name))?;
+    value
+        .parse()
+        .map_err(|_| format!("invalid value for {}: {:?}", name, value))
+}
+
+// Day and input path of a command working on one day. Other arguments go to
+// `option` first, which returns false for those the command doesn't take.
+fn parse_day_args<I: Iterator<Item = String>>(
+    mut args: I,
+    mut option: impl FnMut(&str, &mut I) -> Result<bool, String>,
+) -> Result<(u32, Option<String>), String> {
+    let mut day = None;
+    let mut input = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--input" | "-i" => input = Some(option_value(&arg, &mut args)?),
+            _ if option(&arg, &mut args)? => {}
+            _ if day.is_none() => {
+                day = Some(
+                    arg.parse()
+                        .map_err(|_| format!("day must be a number, got {:?}", arg))?,
+                )
+            }
+            _ => return Err(format!("unexpected argument {:?}", arg)),
         }
-        3 => {
-            println!(
-                "day3 part1: {}",
-                day3::solve1(read_and_parse_input("day3.txt"))
-            );
-            println!(
-                "day3 part2: {}",
-                day3::solve2(read_and_parse_input("day3.txt"))
-            );
+    }
+
+    let day = day.ok_or("missing day")?;
+    check_registered(&[day])?;
+
+    Ok((day, input))
+}
+
+fn parse_bench_args(args: impl Iterator<Item = String>) -> Result<Command, String> {
+    let mut part = None;
+    let mut iterations = 10;
+    let mut warmup = 1;
+    let mut format = Format::Text;
+
+    let (day, input) = parse_day_args(args, |arg, args| {
+        match arg {
+            "--part" => part = Some(option_value::<u8>(arg, args)?),
+            "--iterations" => iterations = option_value(arg, args)?,
+            "--warmup" => warmup = option_value(arg, args)?,
+            "--format" => format = parse_format(args, &[Format::Text, Format::Csv, Format::Json])?,
+            _ => return Ok(false),
         }
-        4 => {
-            println!(
-                "day4 part1: {}",
-                day4::solve1(read_and_parse_input("day4.txt"))
-            );
-            println!(
-                "day4 part2: {}",
-                day4::solve2(read_and_parse_input("day4.txt"))
-            );
+        Ok(true)
+    })?;
+
+    if iterations == 0 {
+        return Err("--iterations must be at least 1".to_string());
+    }
+    let stages = match part {
+        None => vec![Stage::Parse, Stage::Part1, Stage::Part2],
+        Some(1) => vec![Stage::Parse, Stage::Part1],
+        Some(2) => vec![Stage::Parse, Stage::Part2],
+        Some(p) => return Err(format!("part must be 1 or 2, got {}", p)),
+    };
+
+    Ok(Command::Bench {
+        day,
+        input,
+        stages,
+        iterations,
+        warmup,
+        format,
+    })
+}
+
+fn parse_verify_args(mut args: impl Iterator<Item = String>) -> Result<Command, String> {
+    let mut days = None;
+    let mut answers = "inputs/answers.toml".to_string();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--answers" => answers = option_value(&arg, &mut args)?,
+            _ if days.is_none() => days = Some(parse_days(&arg)?),
+            _ => return Err(format!("unexpected argument {:?}", arg)),
         }
-        5 => {
-            println!("day5 part1: {}", day5::solve1(read_input("day5.txt")));
-            println!("day5 part2: {}", day5::solve2(read_input("day5.txt")));
+    }
+
+    let days = days.unwrap_or_else(|| registry().keys().cloned().collect());
+    check_registered(&days)?;
+
+    Ok(Command::Verify { days, answers })
+}
+
+fn parse_profile_args(args: impl Iterator<Item = String>) -> Result<Command, String> {
+    let mut extended = false;
+    let mut steps = 10_000_000;
+    let mut registers = vec![];
+
+    let (day, input) = parse_day_args(args, |arg, args| {
+        match arg {
+            "--extended" => extended = true,
+            "--steps" => steps = option_value(arg, args)?,
+            "--registers" => {
+                let values: String = option_value(arg, args)?;
+                registers = values
+                    .split(',')
+                    .map(|v| v.trim().parse())
+                    .collect::<Result<_, _>>()
+                    .map_err(|_| format!("invalid value for {}: {:?}", arg, values))?;
+            }
+            _ => return Ok(false),
         }
-        6 => {
-            println!(
-                "day6 part1: {}",
-                day6::solve1(read_and_parse_input("day6.txt"))
-            );
-            println!(
-                "day6 part2: {}",
-                day6::solve2(read_and_parse_input("day6.txt"))
-            );
+        Ok(true)
+    })?;
+
+    Ok(Command::Profile {
+        day,
+        input,
+        extended,
+        steps,
+        registers,
+    })
+}
+
+fn parse_analyze_args(args: impl Iterator<Item = String>) -> Result<Command, String> {
+    let mut extended = false;
+    let mut registers = vec![];
+
+    let (day, input) = parse_day_args(args, |arg, args| {
+        match arg {
+            "--extended" => extended = true,
+            "--registers" => {
+                let values: String = option_value(arg, args)?;
+                registers = values
+                    .split(',')
+                    .map(|v| match v.trim() {
+                        "?" => Ok(None),
+                        v => v.parse().map(Some),
+                    })
+                    .collect::<Result<_, _>>()
+                    .map_err(|_| format!("invalid value for {}: {:?}", arg, values))?;
+            }
+            _ => return Ok(false),
         }
-        7 => {
-            println!(
-                "day7 part1: {}",
-                day7::solve1(read_and_parse_input("day7.txt"))
-            );
-            println!(
-                "day7 part2: {}",
-                day7::solve2(read_and_parse_input("day7.txt"), 5, 60)
-            );
+        Ok(true)
+    })?;
+
+    Ok(Command::Analyze {
+        day,
+        input,
+        extended,
+        registers,
+    })
+}
+
+// Day, input path and whether the program uses the extended instruction set.
+fn parse_program_args(
+    args: impl Iterator<Item = String>,
+) -> Result<(u32, Option<String>, bool), String> {
+    let mut extended = false;
+    let (day, input) = parse_day_args(args, |arg, _| match arg {
+        "--extended" => {
+            extended = true;
+            Ok(true)
         }
-        8 => {
-            println!(
-                "day8 part1: {}",
-                day8::solve1(read_and_parse_input("day8.txt"))
-            );
-            println!(
-                "day8 part2: {}",
-                day8::solve2(read_and_parse_input("day8.txt"))
+        _ => Ok(false),
+    })?;
+
+    Ok((day, input, extended))
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Command, String> {
+    let mut days = None;
+    let mut input = None;
+    let mut format = Format::Text;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "bench" if days.is_none() => return parse_bench_args(args),
+            "verify" if days.is_none() => return parse_verify_args(args),
+            "debug" if days.is_none() => {
+                let (day, input, extended) = parse_program_args(args)?;
+                if input.as_deref() == Some("-") {
+                    return Err(
+                        "debug reads commands from stdin, --input - is not supported".to_string(),
+                    );
+                }
+                return Ok(Command::Debug {
+                    day,
+                    input,
+                    extended,
+                });
+            }
+            "profile" if days.is_none() => return parse_profile_args(args),
+            "analyze" if days.is_none() => return parse_analyze_args(args),
+            "assemble" if days.is_none() => {
+                let mut source = None;
+                let mut extended = false;
+                for arg in args {
+                    match arg.as_str() {
+                        "--extended" => extended = true,
+                        _ if source.is_none() => source = Some(arg),
+                        _ => return Err(format!("unexpected argument {:?}", arg)),
+                    }
+                }
+                let source = source.ok_or("missing source path")?;
+                return Ok(Command::Assemble { source, extended });
+            }
+            "decompile" if days.is_none() => {
+                let (day, input, extended) = parse_program_args(args)?;
+                return Ok(Command::Decompile {
+                    day,
+                    input,
+                    extended,
+                });
+            }
+            "transpile" if days.is_none() => {
+                let (day, input, extended) = parse_program_args(args)?;
+                return Ok(Command::Transpile {
+                    day,
+                    input,
+                    extended,
+                });
+            }
+            "halting" if days.is_none() => {
+                let (day, input, extended) = parse_program_args(args)?;
+                return Ok(Command::Halting {
+                    day,
+                    input,
+                    extended,
+                });
+            }
+            "--input" | "-i" => input = Some(option_value(&arg, &mut args)?),
+            "--format" => format = parse_format(&mut args, &[Format::Text, Format::Json])?,
+            _ if days.is_none() => days = Some(parse_days(&arg)?),
+            _ => return Err(format!("unexpected argument {:?}", arg)),
+        }
+    }
+
+    let days = days.ok_or("missing day")?;
+    if input.is_some() && days.len() > 1 {
+        return Err("--input can only be used with a single day".to_string());
+    }
+    check_registered(&days)?;
+
+    Ok(Command::Run {
+        days,
+        input,
+        format,
+    })
+}
+
+fn input_path(day: u32, input: Option<&str>) -> String {
+    input
+        .map(String::from)
+        .unwrap_or_else(|| format!("inputs/day{}.txt", day))
+}
+
+fn solve(day: u32, input: Option<&str>) -> Result<Report, Error> {
+    let days = registry();
+    let path = input_path(day, input);
+    let input = read_input(&path)?;
+
+    run(days[&day].as_ref(), &input).map_err(|e| e.in_file(&path))
+}
+
+fn format_duration(d: Duration) -> String {
+    let micros = d.as_micros();
+    if micros < 1_000 {
+        format!("{}µs", micros)
+    } else if micros < 1_000_000 {
+        format!("{:.1}ms", micros as f64 / 1e3)
+    } else {
+        format!("{:.2}s", micros as f64 / 1e6)
+    }
+}
+
+// Print rows left aligned in columns under header.
+// Rows shorter than the header don't affect column widths.
+fn print_columns(header: &[&str], rows: &[Vec<String>]) {
+    let mut widths: Vec<usize> = header.iter().map(|h| h.chars().count()).collect();
+    for row in rows.iter().filter(|r| r.len() == header.len()) {
+        for (w, cell) in widths.iter_mut().zip(row) {
+            *w = (*w).max(cell.chars().count());
+        }
+    }
+    let print_row = |row: &[String]| {
+        let cells: Vec<String> = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, &w)| format!("{:<w$}", cell, w = w))
+            .collect();
+        println!("{}", cells.join("  ").trim_end());
+    };
+
+    print_row(&header.iter().map(|h| h.to_string()).collect::<Vec<_>>());
+    for row in rows {
+        print_row(row);
+    }
+}
+
+// Run every day and print answers with parse and solve durations in a table.
+// Returns false if some day failed.
+fn print_table(days: &[u32]) -> bool {
+    let mut rows = vec![];
+    let mut ok = true;
+    let mut total = Duration::default();
+
+    for &day in days {
+        let row = match solve(day, None) {
+            Ok(r) => {
+                let time = r.parse_time + r.part1_time + r.part2_time.unwrap_or_default();
+                total += time;
+                vec![
+                    day.to_string(),
+                    r.part1.to_string(),
+                    r.part2.map(|a| a.to_string()).unwrap_or_default(),
+                    format_duration(r.parse_time),
+                    format_duration(r.part1_time),
+                    r.part2_time.map(format_duration).unwrap_or_default(),
+                    format_duration(time),
+                ]
+            }
+            Err(e) => {
+                ok = false;
+                vec![day.to_string(), format!("error: {}", e)]
+            }
+        };
+        rows.push(row);
+    }
+
+    print_columns(
+        &["day", "part1", "part2", "parse", "time1", "time2", "total"],
+        &rows,
+    );
+    println!("total runtime: {}", format_duration(total));
+
+    ok
+}
+
+// Print one JSON record per line for every solved part of days.
+// Returns false if some day failed.
+fn print_json(days: &[u32], input: Option<&str>) -> bool {
+    let mut ok = true;
+
+    for &day in days {
+        match solve(day, input) {
+            Ok(r) => {
+                let parts = [
+                    (1, Some(r.part1), Some(r.part1_time)),
+                    (2, r.part2, r.part2_time),
+                ];
+                for (part, answer, time) in parts.iter().cloned() {
+                    if let (Some(answer), Some(time)) = (answer, time) {
+                        let artifacts = match &answer {
+                            Answer::Image(path) => vec![json::quote(path)],
+                            _ => vec![],
+                        };
+                        println!(
+                            "{{\"day\":{},\"part\":{},\"type\":\"{}\",\"answer\":{},\"parse_ns\":{},\"solve_ns\":{},\"artifacts\":{}}}",
+                            day,
+                            part,
+                            json::answer_type(&answer),
+                            json::answer(&answer),
+                            r.parse_time.as_nanos(),
+                            time.as_nanos(),
+                            json::array(artifacts)
+                        );
+                    }
+                }
+            }
+            Err(e) => {
+                ok = false;
+                println!(
+                    "{{\"day\":{},\"error\":{}}}",
+                    day,
+                    json::quote(&e.to_string())
+                );
+            }
+        }
+    }
+
+    ok
+}
+
+fn print_bench(day: u32, results: &[(Stage, Stats)], format: Format) {
+    match format {
+        Format::Text => {
+            let rows: Vec<Vec<String>> = results
+                .iter()
+                .map(|(stage, s)| {
+                    vec![
+                        stage.to_string(),
+                        s.iterations.to_string(),
+                        format_duration(s.min),
+                        format_duration(s.median),
+                        format_duration(s.mean),
+                        format_duration(s.stddev),
+                    ]
+                })
+                .collect();
+            println!("day{}", day);
+            print_columns(
+                &["stage", "iterations", "min", "median", "mean", "stddev"],
+                &rows,
             );
         }
-        9 => {
-            println!("day9 part1: {}", day9::solve1(479, 71035));
-            println!("day9 part2: {}", day9::solve1(479, 71035 * 100));
+        Format::Json => {
+            for (stage, s) in results {
+                println!(
+                    "{{\"day\":{},\"stage\":\"{}\",\"iterations\":{},\"min_ns\":{},\"median_ns\":{},\"mean_ns\":{},\"stddev_ns\":{}}}",
+                    day,
+                    stage,
+                    s.iterations,
+                    s.min.as_nanos(),
+                    s.median.as_nanos(),
+                    s.mean.as_nanos(),
+                    s.stddev.as_nanos()
+                );
+            }
         }
-        10 => {
-            day10::solve1(read_and_parse_input("day10.txt"), 150_000, 58);
-            println!("day10 part1: see image file.");
+        Format::Csv => {
+            println!("day,stage,iterations,min_ns,median_ns,mean_ns,stddev_ns");
+            for (stage, s) in results {
+                println!(
+                    "{},{},{},{},{},{},{}",
+                    day,
+                    stage,
+                    s.iterations,
+                    s.min.as_nanos(),
+                    s.median.as_nanos(),
+                    s.mean.as_nanos(),
+                    s.stddev.as_nanos()
+                );
+            }
         }
-        11 => {
-            println!("day11 part1: {:?}", day11::solve1(9810));
-            println!("day11 part2: {:?}", day11::solve2(9810));
+    }
+}
+
+// Run days and compare answers to the recorded ones.
+// Returns false if some answer didn't match or a day failed.
+fn verify(days: &[u32], answers: &Answers) -> bool {
+    let mut rows = vec![];
+    let (mut passed, mut failed, mut unrecorded) = (0, 0, 0);
+
+    for &day in days {
+        let report = match solve(day, None) {
+            Ok(r) => r,
+            Err(e) => {
+                failed += 1;
+                rows.push(vec![
+                    day.to_string(),
+                    String::new(),
+                    format!("error: {}", e),
+                ]);
+                continue;
+            }
+        };
+        let parts = [
+            (1, Some(report.part1.to_string())),
+            (2, report.part2.map(|a| a.to_string())),
+        ];
+        for (part, actual) in parts.iter().cloned() {
+            let expected = answers.get(&format!("day{}", day), part);
+            let status = match (expected, &actual) {
+                (None, None) => continue,
+                (None, Some(_)) => {
+                    unrecorded += 1;
+                    "unrecorded"
+                }
+                (Some(e), Some(a)) if e == a => {
+                    passed += 1;
+                    "pass"
+                }
+                _ => {
+                    failed += 1;
+                    "FAIL"
+                }
+            };
+            rows.push(vec![
+                day.to_string(),
+                format!("part{}", part),
+                status.to_string(),
+                expected.unwrap_or_default().to_string(),
+                actual.unwrap_or_else(|| "(no answer)".to_string()),
+            ]);
         }
-        12 => {
-            println!("day12 part1: {}", day12::solve1(read_input("day12.txt")));
-            println!("day12 part2: {}", day12::solve2(read_input("day12.txt")));
+    }
+
+    print_columns(&["day", "part", "status", "expected", "actual"], &rows);
+    println!(
+        "{} passed, {} failed, {} unrecorded",
+        passed, failed, unrecorded
+    );
+
+    failed == 0
+}
+
+// Opcodes of 2018, or with those of extensions.
+fn instruction_set(extended: bool) -> InstructionSet {
+    if extended {
+        extensions::extended()
+    } else {
+        InstructionSet::default()
+    }
+}
+
+// Program of the input, day 16 has its opcodes numbered and decoded from the samples.
+fn load_vm(day: u32, input: Option<&str>, extended: bool) -> Result<VM, Error> {
+    let path = input_path(day, input);
+    let source = read_input(&path)?;
+    if day == 16 {
+        let manual: Manual = source.parse().map_err(|e: ParseError| e.in_file(&path))?;
+        return day16::decode(&manual);
+    }
+    Ok(VM::parse(&source, &instruction_set(extended)).map_err(|e| e.in_file(&path))?)
+}
+
+// Read debugger commands from stdin until quit or end of input.
+fn debug(day: u32, input: Option<&str>, extended: bool) -> Result<(), Error> {
+    let mut debugger = Debugger::new(load_vm(day, input, extended)?);
+    println!(
+        "{} instructions, type help for commands",
+        debugger.vm.program.len()
+    );
+
+    let stdin = io::stdin();
+    let mut stdout = io::stdout();
+    let mut lines = stdin.lock().lines();
+    loop {
+        print!("(elf) ");
+        stdout.flush().ok();
+        let line = match lines.next() {
+            Some(Ok(line)) => line,
+            _ => {
+                println!();
+                break;
+            }
+        };
+        if line.trim().is_empty() {
+            continue;
         }
-        13 => {
-            println!("day13 part1: {:?}", day13::solve1(read_input("day13.txt")));
-            println!("day13 part2: {:?}", day13::solve2(read_input("day13.txt")));
+        match debugger.parse(&line) {
+            Ok(DebugCommand::Quit) => break,
+            Ok(command) => debugger
+                .execute(command, &mut stdout)
+                .map_err(|err| Error::Io {
+                    path: "stdout".to_string(),
+                    err,
+                })?,
+            Err(e) => println!("error: {}", e),
         }
-        14 => {
-            println!("day14 part1: {}", day14::solve1(765071));
-            println!("day14 part2: {}", day14::solve2("765071"));
+    }
+
+    Ok(())
+}
+
+// Remove verbosity flags from args, returning count of -v minus count of -q.
+fn take_verbosity(args: &mut Vec<String>) -> i32 {
+    let mut verbosity = 0;
+    args.retain(|arg| match arg.as_str() {
+        "-q" | "--quiet" => {
+            verbosity -= 1;
+            false
         }
-        15 => {
-            println!("day15 part1: {}", day15::solve1(read_input("day15.txt")));
-            println!("day15 part2: {}", day15::solve2(read_input("day15.txt")));
+        "--verbose" => {
+            verbosity += 1;
+            false
         }
-        16 => {
-            println!("day16 part1: {}", day16::solve1(read_input("day16.txt")));
-            println!("day16 part2: {}", day16::solve2(read_input("day16.txt")));
+        a if a.len() > 1 && a.starts_with('-') && a[1..].chars().all(|c| c == 'v') => {
+            verbosity += a.len() as i32 - 1;
+            false
         }
-        17 => {
-            let (p1, p2) = day17::solve1(read_and_parse_input("day17.txt"));
-            println!("day17 part1: {} part2: {}", p1, p2);
+        _ => true,
+    });
+    verbosity
+}
+
+// Remove --log options from args, returning their filters.
+fn take_log_filters(args: &mut Vec<String>) -> Result<Vec<logger::Filter>, String> {
+    let mut filters = vec![];
+    let mut rest = vec![];
+    let mut iter = args.drain(..);
+    while let Some(arg) = iter.next() {
+        if arg == "--log" {
+            filters.push(option_value(&arg, &mut iter)?);
+        } else {
+            rest.push(arg);
         }
-        18 => {
-            println!("day18 part1: {}", day18::solve1(read_input("day18.txt")));
-            println!("day18 part2: {}", day18::solve2(read_input("day18.txt")));
+    }
+    drop(iter);
+    *args = rest;
+    Ok(filters)
+}
+
+fn main() {
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    let verbosity = take_verbosity(&mut args);
+    let filters = take_log_filters(&mut args).unwrap_or_else(|e| {
+        eprintln!("error: {}\n{}", e, USAGE);
+        process::exit(2);
+    });
+    logger::init(verbosity, filters);
+
+    let command = match parse_args(args.into_iter()) {
+        Ok(command) => command,
+        Err(e) => {
+            eprintln!("error: {}\n{}", e, USAGE);
+            process::exit(2);
         }
-        19 => {
-            println!("day19 part1: {}", day19::solve1(read_input("day19.txt")));
-            println!("day19 part2: {}", day19::solve2(read_input("day19.txt")));
+    };
+
+    match command {
+        Command::Run {
+            days,
+            input,
+            format: Format::Json,
+        } => {
+            if !print_json(&days, input.as_deref()) {
+                process::exit(1);
+            }
         }
-        20 => {
-            println!("day20 part1: {}", day20::solve1(read_input("day20.txt")));
-            println!("day20 part2: {}", day20::solve2(read_input("day20.txt")));
+        Command::Run { days, input, .. } => {
+            if let [day] = days[..] {
+                match solve(day, input.as_deref()) {
+                    Ok(r) => {
+                        println!("day{} part1: {}", day, r.part1);
+                        if let Some(answer) = r.part2 {
+                            println!("day{} part2: {}", day, answer);
+                        }
+                    }
+                    Err(e) => {
+                        eprintln!("error: {}", e);
+                        process::exit(1);
+                    }
+                }
+            } else if !print_table(&days) {
+                process::exit(1);
+            }
         }
-        21 => {
-            println!("day21 part1: {}", day21::solve1(read_input("day21.txt")));
-            println!("day21 part2: {}", day21::solve2(read_input("day21.txt")));
+        Command::Bench {
+            day,
+            input,
+            stages,
+            iterations,
+            warmup,
+            format,
+        } => {
+            let path = input_path(day, input.as_deref());
+            let results = read_input(&path).and_then(|input| {
+                let days = registry();
+                bench(days[&day].as_ref(), &input, &stages, warmup, iterations)
+                    .map_err(|e| e.in_file(&path))
+            });
+            match results {
+                Ok(results) => print_bench(day, &results, format),
+                Err(e) => {
+                    eprintln!("error: {}", e);
+                    process::exit(1);
+                }
+            }
         }
-        22 => {
-            println!("day22 part1: {}", day22::solve1(7740, 12, 763));
-            println!("day22 part2: {}", day22::solve2(7740, 12, 763));
+        Command::Verify { days, answers } => {
+            let answers = read_input(&answers).and_then(|s| {
+                s.parse::<Answers>()
+                    .map_err(|e| Error::from(e.in_file(&answers)))
+            });
+            match answers {
+                Ok(answers) => {
+                    if !verify(&days, &answers) {
+                        process::exit(1);
+                    }
+                }
+                Err(e) => {
+                    eprintln!("error: {}", e);
+                    process::exit(1);
+                }
+            }
         }
-        23 => {
-            println!(
-                "day23 part1: {}",
-                day23::solve1(read_and_parse_input("day23.txt"))
-            );
-            println!(
-                "day23 part2: {}",
-                day23::solve2(read_and_parse_input("day23.txt"))
-            );
+        Command::Debug {
+            day,
+            input,
+            extended,
+        } => {
+            if let Err(e) = debug(day, input.as_deref(), extended) {
+                eprintln!("error: {}", e);
+                process::exit(1);
+            }
         }
-        24 => {
-            println!("day24 part1: {}", day24::solve1(read_input("day24.txt")));
-            println!("day24 part2: {}", day24::solve2(read_input("day24.txt")));
+        Command::Profile {
+            day,
+            input,
+            extended,
+            steps,
+            registers,
+        } => match load_vm(day, input.as_deref(), extended) {
+            Ok(mut vm) if registers.len() <= vm.registers.len() => {
+                vm.registers[..registers.len()].copy_from_slice(&registers);
+                let p = profile(&mut vm.clone(), steps);
+                print!("{}", p.listing(&vm));
+            }
+            Ok(vm) => {
+                eprintln!(
+                    "error: --registers has {} values for {} registers",
+                    registers.len(),
+                    vm.registers.len()
+                );
+                process::exit(2);
+            }
+            Err(e) => {
+                eprintln!("error: {}", e);
+                process::exit(1);
+            }
+        },
+        Command::Analyze {
+            day,
+            input,
+            extended,
+            registers,
+        } => match load_vm(day, input.as_deref(), extended) {
+            Ok(vm) if registers.len() <= vm.registers.len() => {
+                let mut initial: Vec<Interval> = vm
+                    .registers
+                    .iter()
+                    .map(|&v| Interval::constant(v))
+                    .collect();
+                for (i, v) in registers.iter().enumerate() {
+                    initial[i] = v.map_or(Interval::ANY, Interval::constant);
+                }
+                print!("{}", analyze(&vm, &initial).report(&vm));
+            }
+            Ok(vm) => {
+                eprintln!(
+                    "error: --registers has {} values for {} registers",
+                    registers.len(),
+                    vm.registers.len()
+                );
+                process::exit(2);
+            }
+            Err(e) => {
+                eprintln!("error: {}", e);
+                process::exit(1);
+            }
+        },
+        Command::Assemble { source, extended } => {
+            let plain = read_input(&source).and_then(|s| {
+                assemble_with(&s, &instruction_set(extended))
+                    .map_err(|e| Error::from(e.in_file(&source)))
+            });
+            match plain {
+                Ok(plain) => print!("{}", plain),
+                Err(e) => {
+                    eprintln!("error: {}", e);
+                    process::exit(1);
+                }
+            }
         }
-        25 => {
-            println!(
-                "day25 part1: {}",
-                day25::solve1(read_and_parse_input("day25.txt"))
-            );
+        Command::Decompile {
+            day,
+            input,
+            extended,
+        } => match load_vm(day, input.as_deref(), extended) {
+            Ok(vm) => print!("{}", decompile(&vm)),
+            Err(e) => {
+                eprintln!("error: {}", e);
+                process::exit(1);
+            }
+        },
+        Command::Transpile {
+            day,
+            input,
+            extended,
+        } => match load_vm(day, input.as_deref(), extended) {
+            Ok(vm) => print!("{}", transpile_program(&vm)),
+            Err(e) => {
+                eprintln!("error: {}", e);
+                process::exit(1);
+            }
+        },
+        Command::Halting {
+            day,
+            input,
+            extended,
+        } => {
+            let vm = load_vm(day, input.as_deref(), extended).unwrap_or_else(|e| {
+                eprintln!("error: {}", e);
+                process::exit(1);
+            });
+            match halting::analyze(&vm, halting::MAX_STEPS) {
+                Ok(h) => {
+                    let ins = &vm.program[h.compare];
+                    println!(
+                        "halt check {:>4}: {:<20}{}",
+                        h.compare,
+                        ins.to_string(),
+                        vm.decompile(ins)
+                    );
+                    println!("fastest: {}", h.fastest);
+                    println!("slowest: {}", h.slowest);
+                    println!("halting values: {}", h.candidates);
+                }
+                Err(e) => {
+                    eprintln!("error: {}", e);
+                    process::exit(1);
+                }
+            }
         }
-        _ => panic!("invalid day"),
     }
 }
@@ -0,0 +1,305 @@
+// Native replacements for ElfCode loops too slow to execute one instruction at a time.
+//
+// Loops are matched by shape, any registers and constants, and replaced by a
+// superinstruction run when the VM reaches the loop header. It leaves the registers
+// as the loop would and continues at the instruction after the loop.
+use crate::elfcode::OpCode::*;
+use crate::elfcode::{Arithmetic, Instruction, OpCode};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Superinstruction {
+    // do {
+    //     if factor * counter == target { sum += factor }
+    //     counter += 1
+    // } while counter <= target
+    DivisorSum {
+        factor: usize,
+        counter: usize,
+        target: usize,
+        sum: usize,
+        scratch: usize,
+        exit: usize,
+    },
+    // while (quotient + 1) * divisor <= dividend { quotient += 1 }
+    Divide {
+        quotient: usize,
+        dividend: usize,
+        divisor: i64,
+        scratch: usize,
+        exit: usize,
+    },
+}
+
+impl Superinstruction {
+    // Run the loop on registers, returning the instruction to continue at and the
+    // number of instructions the loop would have executed. With checked arithmetic an
+    // overflow gives the offset from the loop header of the instruction it happens in.
+    pub fn execute(&self, r: &mut [i64], arithmetic: Arithmetic) -> Result<(usize, u64), usize> {
+        let checked = arithmetic == Arithmetic::Checked;
+        match *self {
+            Superinstruction::DivisorSum {
+                factor,
+                counter,
+                target,
+                sum,
+                scratch,
+                exit,
+            } => {
+                let (f, t, c) = (r[factor], r[target], r[counter]);
+                let last = c.max(t);
+                let matches = if f == 0 {
+                    if t == 0 {
+                        last - c + 1
+                    } else {
+                        0
+                    }
+                } else if t % f == 0 && (c..=last).contains(&(t / f)) {
+                    1
+                } else {
+                    0
+                };
+                if checked {
+                    // Products grow towards one end of the counter's range.
+                    f.checked_mul(c).and(f.checked_mul(last)).ok_or(0usize)?;
+                    r[sum] = f
+                        .checked_mul(matches)
+                        .and_then(|m| r[sum].checked_add(m))
+                        .ok_or(4usize)?;
+                    r[counter] = last.checked_add(1).ok_or(5usize)?;
+                } else {
+                    r[sum] = r[sum].wrapping_add(f.wrapping_mul(matches));
+                    r[counter] = last.wrapping_add(1);
+                }
+                r[scratch] = 1;
+                // Eight instructions a time around, the last one skips the jump back.
+                let iterations = (last.wrapping_sub(c) as u64).saturating_add(1);
+                Ok((exit, iterations.saturating_mul(8) - 1))
+            }
+            Superinstruction::Divide {
+                quotient,
+                dividend,
+                divisor,
+                scratch,
+                exit,
+            } => {
+                let start = r[quotient];
+                let end = start.max(r[dividend].div_euclid(divisor));
+                if checked {
+                    let next = end.checked_add(1).ok_or(0usize)?;
+                    next.checked_mul(divisor).ok_or(1usize)?;
+                }
+                r[quotient] = end;
+                r[scratch] = 1;
+                // Seven instructions for each increment, five for the last test.
+                let increments = end.wrapping_sub(start) as u64;
+                Ok((exit, increments.saturating_mul(7).saturating_add(5)))
+            }
+        }
+    }
+}
+
+// Registers a, b and c of an instruction with opcode op.
+fn binary(ins: &Instruction, op: OpCode) -> Option<[usize; 3]> {
+    if ins.opcode == op {
+        Some([ins.a as usize, ins.b as usize, ins.c as usize])
+    } else {
+        None
+    }
+}
+
+// The other operand of a commutative instruction if one of them is x.
+fn other(a: usize, b: usize, x: usize) -> Option<usize> {
+    if a == x {
+        Some(b)
+    } else if b == x {
+        Some(a)
+    } else {
+        None
+    }
+}
+
+// "ip = x + ip", skipping the next instruction when x is 1.
+fn is_branch(ins: &Instruction, x: usize, ip: usize) -> bool {
+    binary(ins, addr).is_some_and(|[a, b, c]| c == ip && other(a, b, ip) == Some(x))
+}
+
+// "ip = ip + 1", skipping the next instruction.
+fn is_skip(ins: &Instruction, ip: usize) -> bool {
+    ins.opcode == addi && ins.a as usize == ip && ins.b == 1 && ins.c as usize == ip
+}
+
+// "ip = target - 1", jumping to target.
+fn jump_target(ins: &Instruction, ip: usize) -> Option<usize> {
+    if ins.opcode == seti && ins.c as usize == ip && ins.a >= -1 {
+        Some((ins.a + 1) as usize)
+    } else {
+        None
+    }
+}
+
+fn distinct(registers: &[usize]) -> bool {
+    registers
+        .iter()
+        .enumerate()
+        .all(|(i, r)| !registers[i + 1..].contains(r))
+}
+
+fn divisor_sum(program: &[Instruction], h: usize, ip: usize) -> Option<Superinstruction> {
+    let ins = program.get(h..h + 9)?;
+    let [x, y, scratch] = binary(&ins[0], mulr)?;
+    let [a, b, c] = binary(&ins[1], eqrr)?;
+    let target = other(a, b, scratch).filter(|_| c == scratch)?;
+    if !is_branch(&ins[2], scratch, ip) || !is_skip(&ins[3], ip) {
+        return None;
+    }
+    let [a, b, sum] = binary(&ins[4], addr)?;
+    let factor = other(a, b, sum)?;
+    let counter = other(x, y, factor)?;
+    let inc = &ins[5];
+    if inc.opcode != addi || inc.a as usize != counter || inc.b != 1 || inc.c as usize != counter {
+        return None;
+    }
+    if binary(&ins[6], gtrr)? != [counter, target, scratch]
+        || !is_branch(&ins[7], scratch, ip)
+        || jump_target(&ins[8], ip)? != h
+        || !distinct(&[factor, counter, target, sum, scratch, ip])
+    {
+        return None;
+    }
+
+    Some(Superinstruction::DivisorSum {
+        factor,
+        counter,
+        target,
+        sum,
+        scratch,
+        exit: h + 9,
+    })
+}
+
+fn divide(program: &[Instruction], h: usize, ip: usize) -> Option<Superinstruction> {
+    let ins = program.get(h..h + 8)?;
+    let (quotient, scratch) = (ins[0].a as usize, ins[0].c as usize);
+    if ins[0].opcode != addi || ins[0].b != 1 {
+        return None;
+    }
+    let mul = &ins[1];
+    let divisor = mul.b;
+    if mul.opcode != muli || mul.a as usize != scratch || mul.c as usize != scratch || divisor <= 0
+    {
+        return None;
+    }
+    let [a, dividend, c] = binary(&ins[2], gtrr)?;
+    let inc = &ins[6];
+    if a != scratch
+        || c != scratch
+        || !is_branch(&ins[3], scratch, ip)
+        || !is_skip(&ins[4], ip)
+        || inc.opcode != addi
+        || inc.a as usize != quotient
+        || inc.b != 1
+        || inc.c as usize != quotient
+        || jump_target(&ins[7], ip)? != h
+        || !distinct(&[quotient, dividend, scratch, ip])
+    {
+        return None;
+    }
+
+    Some(Superinstruction::Divide {
+        quotient,
+        dividend,
+        divisor,
+        scratch,
+        exit: jump_target(&ins[5], ip)?,
+    })
+}
+
+// Superinstruction for each instruction starting a loop that has one.
+pub fn optimize(program: &[Instruction], ip: usize) -> Vec<Option<Superinstruction>> {
+    (0..program.len())
+        .map(|h| divisor_sum(program, h, ip).or_else(|| divide(program, h, ip)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::elfcode::{VmError, VM};
+
+    // Run the program with and without optimization, returning registers, calls to
+    // step and instructions counted.
+    fn run(program: &str, registers: &[i64], optimize: bool) -> (Vec<i64>, usize, u64) {
+        let mut vm: VM = program.parse().unwrap();
+        vm.registers = registers.to_vec();
+        if optimize {
+            vm.optimize();
+        }
+        let mut steps = 0;
+        while vm.step().unwrap() {
+            steps += 1;
+        }
+        (vm.registers, steps, vm.steps)
+    }
+
+    #[test]
+    fn test_divisor_sum() {
+        let program = "#ip 3
+seti 1 0 1
+seti 1 0 5
+mulr 1 5 2
+eqrr 2 4 2
+addr 2 3 3
+addi 3 1 3
+addr 1 0 0
+addi 5 1 5
+gtrr 5 4 2
+addr 3 2 3
+seti 1 0 3
+addi 1 1 1
+gtrr 1 4 2
+addr 2 3 3
+seti 0 0 3
+mulr 3 3 3";
+        let registers = [0, 0, 0, 0, 36, 0];
+        let (expected, slow, counted) = run(program, &registers, false);
+        let (actual, fast, fast_counted) = run(program, &registers, true);
+        assert_eq!(expected[0], 1 + 2 + 3 + 4 + 6 + 9 + 12 + 18 + 36);
+        assert_eq!(actual, expected);
+        assert!(fast < slow / 10);
+        assert_eq!(fast_counted, counted);
+
+        // Overflowing the sum fails like the loop would, unless wrapping.
+        let mut vm: VM = program.parse().unwrap();
+        vm.registers = vec![i64::MAX - 10, 0, 0, 0, 36, 0];
+        vm.optimize();
+        assert!(matches!(vm.run(), Err(VmError::Overflow { ip: 6, .. })));
+        let mut vm: VM = program.parse().unwrap();
+        vm.registers = vec![0, 0, 0, 0, 36, 0];
+        vm.optimize();
+        vm.max_steps = Some(counted - 1);
+        assert_eq!(vm.run(), Err(VmError::StepLimit(counted - 1)));
+    }
+
+    #[test]
+    fn test_divide() {
+        let program = "#ip 5
+seti 0 0 3
+addi 3 1 1
+muli 1 256 1
+gtrr 1 4 1
+addr 1 5 5
+addi 5 1 5
+seti 8 0 5
+addi 3 1 3
+seti 0 0 5
+setr 3 0 4";
+        for &dividend in &[0, 255, 256, 65535, 1_000_000] {
+            let registers = [0, 0, 0, 0, dividend, 0];
+            let (expected, _, counted) = run(program, &registers, false);
+            let (actual, fast, fast_counted) = run(program, &registers, true);
+            assert_eq!(expected[4], dividend / 256);
+            assert_eq!(actual, expected);
+            assert_eq!(fast, 3);
+            assert_eq!(fast_counted, counted);
+        }
+    }
+}
@@ -0,0 +1,134 @@
+// Execution profile of an ElfCode program.
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use crate::elfcode::{VmError, VM};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Profile {
+    pub steps: u64,
+    // Whether the program halted, otherwise it ran into the step limit.
+    pub halted: bool,
+    // Error stopping the program early.
+    pub error: Option<VmError>,
+    // Times each instruction was executed.
+    pub hits: Vec<u64>,
+    // Times control went from an instruction back to the same or an earlier one.
+    pub back_edges: BTreeMap<(usize, i64), u64>,
+    // Smallest and largest value seen in each register.
+    pub ranges: Vec<(i64, i64)>,
+}
+
+// Run vm for at most max_steps steps, recording where time goes.
+pub fn profile(vm: &mut VM, max_steps: u64) -> Profile {
+    let mut p = Profile {
+        steps: 0,
+        halted: false,
+        error: None,
+        hits: vec![0; vm.program.len()],
+        back_edges: BTreeMap::new(),
+        ranges: vm.registers.iter().map(|&v| (v, v)).collect(),
+    };
+
+    while p.steps < max_steps {
+        let ip = vm.ip;
+        match vm.step() {
+            Ok(true) => {}
+            Ok(false) => {
+                p.halted = true;
+                break;
+            }
+            Err(e) => {
+                p.error = Some(e);
+                break;
+            }
+        }
+        p.steps += 1;
+        p.hits[ip as usize] += 1;
+        if vm.ip <= ip {
+            *p.back_edges.entry((ip as usize, vm.ip)).or_insert(0) += 1;
+        }
+        for (range, &v) in p.ranges.iter_mut().zip(vm.registers.iter()) {
+            range.0 = range.0.min(v);
+            range.1 = range.1.max(v);
+        }
+    }
+
+    p
+}
+
+impl Profile {
+    // Program listing with hit counts and share of all steps per instruction,
+    // followed by back edges by frequency and register ranges.
+    pub fn listing(&self, vm: &VM) -> String {
+        let mut out = String::new();
+        let share = |n: u64| 100.0 * n as f64 / self.steps.max(1) as f64;
+
+        let state = if self.halted { "halted" } else { "stopped" };
+        writeln!(out, "{} after {} steps", state, self.steps).unwrap();
+        if let Some(e) = &self.error {
+            writeln!(out, "error: {}", e).unwrap();
+        }
+        writeln!(out, "{:>12} {:>6} {:>4}  instruction", "hits", "%", "ip").unwrap();
+        for (ip, (ins, &hits)) in vm.program.iter().zip(self.hits.iter()).enumerate() {
+            writeln!(
+                out,
+                "{:>12} {:>6.2} {:>4}  {:<20}{}",
+                hits,
+                share(hits),
+                ip,
+                ins.to_string(),
+                vm.decompile(ins)
+            )
+            .unwrap();
+        }
+
+        let mut back_edges: Vec<_> = self.back_edges.iter().collect();
+        back_edges.sort_by_key(|&(edge, &n)| (std::cmp::Reverse(n), *edge));
+        writeln!(out, "back edges:").unwrap();
+        for ((from, to), n) in back_edges {
+            writeln!(out, "{:>12} {:>6.2} {:>4} -> {}", n, share(*n), from, to).unwrap();
+        }
+
+        writeln!(out, "register ranges:").unwrap();
+        for (name, (min, max)) in vm.register_names().iter().zip(self.ranges.iter()) {
+            writeln!(out, "{:>4}  {}..={}", name, min, max).unwrap();
+        }
+
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROGRAM: &str = "#ip 2
+seti 0 0 0
+addi 0 1 0
+gtri 0 4 1
+addr 1 2 2
+seti 0 0 2
+seti 9 0 1";
+
+    #[test]
+    fn test_profile() {
+        let mut vm: VM = PROGRAM.parse().unwrap();
+        let p = profile(&mut vm, 1000);
+        assert!(p.halted);
+        assert_eq!(p.steps, 1 + 5 * 3 + 4 + 1);
+        assert_eq!(p.hits, vec![1, 5, 5, 5, 4, 1]);
+        assert_eq!(
+            p.back_edges.into_iter().collect::<Vec<_>>(),
+            vec![((4, 1), 4)]
+        );
+        assert_eq!(p.ranges[0], (0, 5));
+        assert_eq!(p.ranges[1], (0, 9));
+
+        let mut vm: VM = PROGRAM.parse().unwrap();
+        let p = profile(&mut vm, 5);
+        assert!(!p.halted);
+        assert_eq!(p.steps, 5);
+        assert!(p.listing(&vm).starts_with("stopped after 5 steps\n"));
+    }
+}
@@ -0,0 +1,89 @@
+use std::any::Any;
+use std::time::{Duration, Instant};
+
+use crate::answer::Answer;
+use crate::error::{Error, ParseError};
+
+// Common interface of every day: parse input once, then solve parts from it.
+// Parsing only checks the input's format, solving reports what else goes wrong.
+pub trait Solution {
+    type Input: Clone + 'static;
+
+    fn parse(&self, input: &str) -> Result<Self::Input, ParseError>;
+
+    fn part1(&self, input: Self::Input) -> Result<Answer, Error>;
+
+    // None for days without a second part.
+    fn part2(&self, _input: Self::Input) -> Result<Option<Answer>, Error> {
+        Ok(None)
+    }
+}
+
+// Type erased Solution, so that days can be stored in the registry.
+pub trait Runner {
+    fn parse(&self, input: &str) -> Result<Box<dyn Any>, ParseError>;
+
+    // Solvers consume their input, clone it first to solve more than once.
+    fn clone_input(&self, input: &dyn Any) -> Box<dyn Any>;
+
+    fn part1(&self, input: Box<dyn Any>) -> Result<Answer, Error>;
+
+    fn part2(&self, input: Box<dyn Any>) -> Result<Option<Answer>, Error>;
+}
+
+fn downcast<T: 'static>(input: Box<dyn Any>) -> T {
+    *input.downcast::<T>().expect("input parsed by another day")
+}
+
+impl<S: Solution> Runner for S {
+    fn parse(&self, input: &str) -> Result<Box<dyn Any>, ParseError> {
+        Solution::parse(self, input).map(|i| Box::new(i) as Box<dyn Any>)
+    }
+
+    fn clone_input(&self, input: &dyn Any) -> Box<dyn Any> {
+        let input: &S::Input = input.downcast_ref().expect("input parsed by another day");
+        Box::new(input.clone())
+    }
+
+    fn part1(&self, input: Box<dyn Any>) -> Result<Answer, Error> {
+        Solution::part1(self, downcast(input))
+    }
+
+    fn part2(&self, input: Box<dyn Any>) -> Result<Option<Answer>, Error> {
+        Solution::part2(self, downcast(input))
+    }
+}
+
+// Answers of one day with the time spent parsing and solving each part.
+#[derive(Debug, Clone)]
+pub struct Report {
+    pub part1: Answer,
+    pub part2: Option<Answer>,
+    pub parse_time: Duration,
+    pub part1_time: Duration,
+    pub part2_time: Option<Duration>,
+}
+
+// Parse input and solve both parts with runner.
+pub fn run(runner: &dyn Runner, input: &str) -> Result<Report, Error> {
+    let start = Instant::now();
+    let parsed = runner.parse(input)?;
+    let parse_time = start.elapsed();
+
+    let input = runner.clone_input(parsed.as_ref());
+    let start = Instant::now();
+    let part1 = runner.part1(input)?;
+    let part1_time = start.elapsed();
+
+    let start = Instant::now();
+    let part2 = runner.part2(parsed)?;
+    let part2_time = part2.as_ref().map(|_| start.elapsed());
+
+    Ok(Report {
+        part1,
+        part2,
+        parse_time,
+        part1_time,
+        part2_time,
+    })
+}
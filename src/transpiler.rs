@@ -0,0 +1,361 @@
+// Translate an ElfCode program into Rust source.
+//
+// The program becomes `fn program(r: [i64; N]) -> Result<[i64; N], &str>`, a loop
+// dispatching on ip with one match arm per basic block, returning the registers when
+// it halts. Additions and multiplications that overflow and extension opcodes
+// without a result, like division by zero, return the error the VM gives instead.
+// Reads of the bound ip register are replaced by the instruction index, so the
+// register is only written when a block ends. Programs with indirect jumps get
+// an arm per instruction since they can jump into the middle of a block.
+use std::fmt::Write;
+
+use crate::decompiler::{Cfg, Exit};
+use crate::elfcode::{Arithmetic, Instruction, Io, OpCode, Operand, VmError, VM};
+
+// Main running `program` with registers from the command line, printing the result,
+// and the `input` and `output` of extension opcodes on stdin and stdout.
+pub const MAIN: &str = r#"
+// Next value of the input, one per line of stdin.
+#[allow(dead_code)]
+fn input() -> Option<i64> {
+    let mut line = String::new();
+    std::io::stdin().read_line(&mut line).ok()?;
+    line.trim().parse().ok()
+}
+
+// Print a value to the output, returning it.
+#[allow(dead_code)]
+fn output(v: i64) -> i64 {
+    println!("{}", v);
+    v
+}
+
+fn main() {
+    let mut r = [0; REGISTERS];
+    for (i, v) in std::env::args().skip(1).enumerate() {
+        r[i] = v.parse().expect("register value");
+    }
+    let r = match program(r) {
+        Ok(r) => r,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let r: Vec<String> = r.iter().map(|v| v.to_string()).collect();
+    println!("{}", r.join(","));
+}
+"#;
+
+fn operand(vm: &VM, op: Operand, i: usize) -> String {
+    match op {
+        Operand::Register(r) if Some(r) == vm.ip_register => i.to_string(),
+        Operand::Register(r) => format!("r[{}]", r),
+        Operand::Immediate(v) => v.to_string(),
+        Operand::Unused => String::new(),
+    }
+}
+
+// Method of i64 doing the arithmetic of add and mul opcodes.
+fn arithmetic(opcode: OpCode) -> Option<&'static str> {
+    match opcode {
+        OpCode::addr | OpCode::addi => Some("add"),
+        OpCode::mulr | OpCode::muli => Some("mul"),
+        _ => None,
+    }
+}
+
+fn expression(vm: &VM, ins: &Instruction, i: usize) -> String {
+    let [a, b] = ins.inputs();
+    let (a, b) = (operand(vm, a, i), operand(vm, b, i));
+    if let Some(method) = arithmetic(ins.opcode) {
+        return match vm.arithmetic {
+            Arithmetic::Wrapping => format!("i64::wrapping_{}({}, {})", method, a, b),
+            Arithmetic::Checked => {
+                let err = VmError::Overflow {
+                    ip: i as i64,
+                    instruction: *ins,
+                };
+                format!(
+                    "i64::checked_{}({}, {}).ok_or({:?})?",
+                    method,
+                    a,
+                    b,
+                    err.to_string()
+                )
+            }
+        };
+    }
+    match ins.opcode.operator() {
+        None => match ins.opcode {
+            OpCode::Extension(op) => match op.io() {
+                Some(Io::Output) => op.expression(&a, &b),
+                Some(Io::Input) => {
+                    let err = VmError::NoInput { ip: i as i64 };
+                    format!("input().ok_or({:?})?", err.to_string())
+                }
+                None => {
+                    let err = VmError::Undefined {
+                        ip: i as i64,
+                        instruction: *ins,
+                    };
+                    format!(
+                        "{}.ok_or({:?})?",
+                        op.checked_expression(&a, &b),
+                        err.to_string()
+                    )
+                }
+            },
+            _ => ins.opcode.expression(&a, &b),
+        },
+        Some(op) if ins.opcode.is_comparison() => format!("({} {} {}) as i64", a, op, b),
+        Some(op) => format!("{} {} {}", a, op, b),
+    }
+}
+
+// Instruction ranges of the match arms.
+fn arms(vm: &VM) -> Vec<(usize, usize)> {
+    let cfg = Cfg::new(vm);
+    let indirect = cfg
+        .blocks
+        .iter()
+        .any(|b| matches!(b.exit, Exit::Indirect(_)));
+    if indirect {
+        (0..vm.program.len()).map(|i| (i, i + 1)).collect()
+    } else {
+        cfg.blocks.iter().map(|b| (b.start, b.end)).collect()
+    }
+}
+
+// The `program` function.
+pub fn transpile(vm: &VM) -> String {
+    let mut out = String::new();
+    let n = vm.registers.len();
+    match vm.ip_register {
+        Some(ip) => writeln!(out, "// ElfCode program with ip bound to register {}.", ip),
+        None => writeln!(out, "// ElfCode program without ip binding."),
+    }
+    .unwrap();
+    writeln!(
+        out,
+        "pub fn program(mut r: [i64; {}]) -> Result<[i64; {}], &'static str> {{",
+        n, n
+    )
+    .unwrap();
+    writeln!(out, "    let mut ip: i64 = 0;").unwrap();
+    writeln!(out, "    loop {{").unwrap();
+    writeln!(out, "        ip = match ip {{").unwrap();
+
+    for (start, end) in arms(vm) {
+        writeln!(out, "            {} => {{", start).unwrap();
+        for i in start..end {
+            let ins = &vm.program[i];
+            writeln!(
+                out,
+                "                r[{}] = {};",
+                ins.c,
+                expression(vm, ins, i)
+            )
+            .unwrap();
+        }
+        // The last instruction either jumped by writing ip or falls through.
+        let next = match vm.ip_register {
+            Some(ip) if vm.program[end - 1].c as usize == ip => format!("r[{}] + 1", ip),
+            Some(ip) => {
+                writeln!(out, "                r[{}] = {};", ip, end - 1).unwrap();
+                end.to_string()
+            }
+            None => end.to_string(),
+        };
+        writeln!(out, "                {}", next).unwrap();
+        writeln!(out, "            }}").unwrap();
+    }
+
+    writeln!(out, "            _ => return Ok(r),").unwrap();
+    writeln!(out, "        }};").unwrap();
+    writeln!(out, "    }}").unwrap();
+    writeln!(out, "}}").unwrap();
+
+    out
+}
+
+// A complete program with `main` from `MAIN`.
+pub fn transpile_program(vm: &VM) -> String {
+    let main = MAIN.replace("REGISTERS", &vm.registers.len().to_string());
+    format!("{}{}", transpile(vm), main)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extensions::extended;
+    use std::fs;
+    use std::io::Write as _;
+    use std::process::{Command, Output, Stdio};
+
+    const PROGRAM: &str = "#ip 5
+seti 0 0 0
+addi 1 1 1
+addr 0 1 0
+eqri 1 10 2
+addr 2 5 5
+seti 0 0 5
+mulr 0 5 3";
+
+    #[test]
+    fn test_transpile() {
+        let vm: VM = PROGRAM.parse().unwrap();
+        assert_eq!(
+            transpile(&vm),
+            "// ElfCode program with ip bound to register 5.
+pub fn program(mut r: [i64; 6]) -> Result<[i64; 6], &'static str> {
+    let mut ip: i64 = 0;
+    loop {
+        ip = match ip {
+            0 => {
+                r[0] = 0;
+                r[5] = 0;
+                1
+            }
+            1 => {
+                r[1] = i64::checked_add(r[1], 1).ok_or(\"overflow in instruction 1: addi 1 1 1\")?;
+                r[0] = i64::checked_add(r[0], r[1]).ok_or(\"overflow in instruction 2: addr 0 1 0\")?;
+                r[2] = (r[1] == 10) as i64;
+                r[5] = i64::checked_add(r[2], 4).ok_or(\"overflow in instruction 4: addr 2 5 5\")?;
+                r[5] + 1
+            }
+            5 => {
+                r[5] = 0;
+                r[5] + 1
+            }
+            6 => {
+                r[3] = i64::checked_mul(r[0], 6).ok_or(\"overflow in instruction 6: mulr 0 5 3\")?;
+                r[5] = 6;
+                7
+            }
+            _ => return Ok(r),
+        };
+    }
+}
+"
+        );
+    }
+
+    // Compile the program with rustc and run it with registers from args.
+    fn compile_and_run(vm: &VM, name: &str, args: &[&str], stdin: &str) -> Output {
+        let dir = std::env::temp_dir().join(format!("elfcode-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let source = dir.join("program.rs");
+        let binary = dir.join("program");
+        fs::write(&source, transpile_program(vm)).unwrap();
+
+        let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+        let status = Command::new(rustc)
+            .arg(&source)
+            .arg("-o")
+            .arg(&binary)
+            .status()
+            .unwrap();
+        assert!(status.success());
+        let mut child = Command::new(&binary)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(stdin.as_bytes())
+            .unwrap();
+        let output = child.wait_with_output().unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        output
+    }
+
+    // Compare the compiled program's result with the VM.
+    #[test]
+    fn test_compiled() {
+        let vm: VM = PROGRAM.parse().unwrap();
+        let output = compile_and_run(&vm, "builtin", &["0", "3"], "");
+
+        let mut vm = vm;
+        vm.registers[1] = 3;
+        vm.run().unwrap();
+        let expected: Vec<String> = vm.registers.iter().map(|v| v.to_string()).collect();
+        assert_eq!(
+            String::from_utf8(output.stdout).unwrap().trim(),
+            expected.join(",")
+        );
+    }
+
+    // Addition and multiplication overflow where the VM's do.
+    #[test]
+    fn test_compiled_overflow() {
+        let vm: VM = "addr 0 1 2\nmulr 2 1 3".parse().unwrap();
+        for (name, args) in [
+            ("addr", ["9223372036854775800", "8"]),
+            ("mulr", ["4611686018427387904", "2"]),
+        ] {
+            let (a, b) = (args[0].parse().unwrap(), args[1].parse().unwrap());
+            let output = compile_and_run(&vm, name, &args, "");
+            let mut vm = vm.clone();
+            vm.registers[0] = a;
+            vm.registers[1] = b;
+            let err = vm.run().unwrap_err();
+            assert!(matches!(err, VmError::Overflow { .. }));
+            assert!(!output.status.success());
+            assert_eq!(
+                String::from_utf8(output.stderr).unwrap().trim(),
+                format!("error: {}", err)
+            );
+        }
+    }
+
+    // Division fails where the VM's does, e.g. for i64::MIN / -1.
+    #[test]
+    fn test_compiled_extended() {
+        let program = "divr 0 1 2\nmodi 2 7 3";
+        let vm = VM::parse(program, &extended()).unwrap();
+        assert!(transpile(&vm).contains(
+            "r[2] = i64::checked_div(r[0], r[1]).ok_or(\"no result for instruction 0: divr 0 1 2\")?;"
+        ));
+
+        let output = compile_and_run(&vm, "extended", &["100", "3"], "");
+        assert_eq!(
+            String::from_utf8(output.stdout).unwrap().trim(),
+            "100,3,33,5,0,0"
+        );
+
+        let min = i64::MIN.to_string();
+        let output = compile_and_run(&vm, "overflow", &[&min, "-1"], "");
+        let mut vm = vm;
+        vm.registers[0] = i64::MIN;
+        vm.registers[1] = -1;
+        let err = vm.run().unwrap_err();
+        assert!(!output.status.success());
+        assert_eq!(
+            String::from_utf8(output.stderr).unwrap().trim(),
+            format!("error: {}", err)
+        );
+    }
+
+    // Input comes from stdin and output goes to stdout, running out of input fails.
+    #[test]
+    fn test_compiled_io() {
+        let vm = VM::parse("in 0 0 0\nmuli 0 2 1\nout 1 0 1\nin 0 0 0", &extended()).unwrap();
+        let output = compile_and_run(&vm, "io", &[], "21\n");
+        assert_eq!(String::from_utf8(output.stdout).unwrap(), "42\n");
+
+        let mut vm = vm;
+        vm.input.push_back(21);
+        let err = vm.run().unwrap_err();
+        assert_eq!(vm.output[0].value, 42);
+        assert_eq!(
+            String::from_utf8(output.stderr).unwrap().trim(),
+            format!("error: {}", err)
+        );
+    }
+}